@@ -1,98 +1,231 @@
 use {
-    common::git,
+    crate::{
+        branches::{self, Branch},
+        picker::{self, Picked},
+        refname::check_branch_name,
+        switch::switch,
+        worktree,
+    },
+    common::{git, protected::Protected},
     eyre::Result,
     inquire::{
+        error::InquireResult,
         ui::{Attributes, Color, RenderConfig, StyleSheet, Styled},
         Confirm, Select,
     },
-    lool::{cli::stylize::stylize, fail},
+    lool::{cli::stylize::stylize, fail, s},
+    std::fmt::{self, Display},
 };
 
-pub fn select_checkout() -> Result<()> {
-    // Run `git branch --all --no-color`
-    let output = git::exec("branch", vec!["--all", "--no-color"])?;
+/// A branch of the picker, with its name and date padded to the widest ones so the metadata
+/// columns line up
+struct Choice {
+    branch: Branch,
+    widths: (usize, usize),
+}
 
-    // Parse the output to get the list of branches
-    let mut branches = Vec::new();
-    for line in output.lines() {
-        let line = line.trim();
-        if line.starts_with('*') {
-            // Current branch, skip
-            continue;
+impl Display for Choice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let branch = &self.branch;
+
+        let (name_width, date_width) = self.widths;
+
+        let name = format!("{:<name_width$}", branch.name);
+        let name = if branch.remote.is_some() {
+            stylize(name, "magenta")
+        } else {
+            name
+        };
+
+        let mut track = String::new();
+        if branch.gone {
+            track = stylize(" gone", "red");
         } else {
-            // Clean up branch names
-            let branch_name = line.trim().to_string();
-            branches.push(branch_name);
+            if branch.ahead > 0 {
+                track.push_str(&stylize(format!(" ↑{}", branch.ahead), "green"));
+            }
+            if branch.behind > 0 {
+                track.push_str(&stylize(format!(" ↓{}", branch.behind), "yellow"));
+            }
         }
+
+        let upstream = match &branch.upstream {
+            Some(upstream) => format!(" {}{}", stylize(upstream, "+dim"), track),
+            None => s!(""),
+        };
+
+        let worktree = match &branch.worktree {
+            Some(path) => format!(" {}", stylize(format!("⌂ {path}"), "blue")),
+            None => s!(""),
+        };
+
+        write!(
+            f,
+            "{}  {}  {}  {}{}{}",
+            name,
+            stylize(format!("{:<date_width$}", branch.date), "+dim"),
+            stylize(&branch.author, "cyan"),
+            truncate(&branch.subject, 50),
+            upstream,
+            worktree
+        )?;
+
+        // the first line of the description, as a dimmed second line
+        if let Some(description) = branch.description.as_ref().and_then(|d| d.lines().next()) {
+            write!(f, "\n  {}", stylize(truncate(description, 80), "+dim"))?;
+        }
+
+        Ok(())
     }
+}
 
-    // Remove duplicates and sort
-    // Sort branches with "main" or "master" at the top, then the rest alphabetically
-    branches.sort_by(|a, b| {
-        match (a.as_str(), b.as_str()) {
-            ("main", _) | (_, "main") => {
-                if a == "main" {
-                    std::cmp::Ordering::Less
-                } else {
-                    std::cmp::Ordering::Greater
-                }
-            }
-            ("master", _) | (_, "master") => {
-                if a == "master" {
-                    std::cmp::Ordering::Less
-                } else {
-                    std::cmp::Ordering::Greater
-                }
+/// Shows a fuzzy-searchable picker of the branches, most recently committed first, and checks out
+/// the selected one. `Tab` toggles between local branches only and all branches (including the
+/// remote-tracking ones), which are shown right away if there are no other local branches.
+pub fn select_checkout() -> Result<()> {
+    let mut all = false;
+
+    loop {
+        let branches: Vec<Branch> =
+            branches::list(all)?.into_iter().filter(|branch| !branch.current).collect();
+
+        if branches.is_empty() {
+            if all {
+                println!("No branches found.");
+                return Ok(());
             }
-            _ => a.cmp(b), // Regular alphabetical sorting for other branches
+
+            println!("{}", stylize("No other local branches, showing all branches", "+dim"));
+            all = true;
+            continue;
         }
-    });
 
-    branches.dedup();
+        let widths = (
+            branches.iter().map(|branch| branch.name.chars().count()).max().unwrap_or(0),
+            branches.iter().map(|branch| branch.date.chars().count()).max().unwrap_or(0),
+        );
+        let choices: Vec<Choice> =
+            branches.into_iter().map(|branch| Choice { branch, widths }).collect();
 
-    // If no branches found
-    if branches.is_empty() {
-        println!("No branches found.");
-        return Ok(());
-    }
-
-    // Use Select prompt
-    let ans = Select::new("Select a branch to checkout", branches)
-        .with_page_size(10)
-        .without_help_message()
-        .with_render_config(RenderConfig {
-            highlighted_option_prefix: Styled::new(">").with_fg(Color::LightBlue),
-            selected_option: Some(
-                StyleSheet::new().with_attr(Attributes::ITALIC).with_fg(Color::LightBlue),
-            ),
-            answer: StyleSheet::new().with_attr(Attributes::ITALIC).with_fg(Color::LightBlue),
-            help_message: StyleSheet::new().with_fg(Color::White),
-            ..Default::default()
-        })
-        .prompt();
+        let (message, help) = if all {
+            (
+                "Select a branch to checkout (all)",
+                "↑↓ to move, type to filter, tab to show local branches, esc to cancel",
+            )
+        } else {
+            (
+                "Select a branch to checkout (local)",
+                "↑↓ to move, type to filter, tab to show all branches, esc to cancel",
+            )
+        };
 
-    match ans {
-        Ok(choice) => {
-            // Perform `git checkout <choice>`
-            let output = git::exec("checkout", vec![&choice])?;
-            println!("{}", output);
-            Ok(())
+        // filtering by branch name only
+        match picker::pick(message, help, choices, &|choice: &Choice| &choice.branch.name)? {
+            Picked::Option(choice) => return checkout_branch(&choice.branch),
+            Picked::Toggle => all = !all,
+            Picked::Cancel => return Ok(()),
+        }
+    }
+}
+
+/// Truncate a text to `max` chars, adding an ellipsis if it was truncated
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return s!(text);
+    }
+
+    format!("{}…", text.chars().take(max - 1).collect::<String>())
+}
+
+/// Checkout a branch of the picker: local branches are checked out as they are, while for
+/// remote-tracking ones a local branch tracking them is created (`git checkout --track`) instead
+/// of ending up in detached HEAD. Branches checked out in another worktree can't be checked out
+/// here, so going to that worktree is offered instead.
+fn checkout_branch(branch: &Branch) -> Result<()> {
+    if let Some(path) = &branch.worktree {
+        worktree::offer_cd(&branch.name, path)
+    } else if branch.remote.is_some() {
+        switch(&branch.name, vec!["--track", &branch.name])
+    } else {
+        switch(&branch.name, vec![&branch.name])
+    }
+}
+
+/// The remote-tracking branch to track for a branch that only exists in remotes (e.g.
+/// `origin/feat/login` for `feat/login`), asking which one if it exists in several remotes
+pub fn pick_remote(branch: &str) -> Result<Option<String>> {
+    let mut on_remotes = branches::on_remotes(branch)?;
+
+    match on_remotes.len() {
+        0 => Ok(None),
+        1 => Ok(Some(on_remotes.remove(0).name)),
+        _ => {
+            let names = on_remotes.into_iter().map(|remote| remote.name).collect();
+            let ans = Select::new(
+                &format!("'{branch}' exists in several remotes, which one to track?"),
+                names,
+            )
+            .without_help_message()
+            .with_render_config(render_config())
+            .prompt();
+
+            answer(ans).map(Some)
         }
-        Err(err) => fail!("{}", stylize(err.to_string(), "red")),
     }
 }
 
+/// Checkout a branch by name:
+///
+/// - if a local branch with that name exists, it's checked out
+/// - if it's a remote-tracking branch (e.g. `origin/feat/login`), a local branch tracking it is
+///   created, unless it already exists locally
+/// - if the name exists in one or more remotes, a local branch tracking it is created, asking
+///   which remote to track if there are several
+/// - otherwise, it's checked out as is if it exists (e.g. a tag or a commit) or a new branch is
+///   created (asking first for confirmation)
+///
+/// Local changes are taken care of before switching (see `switch`).
 pub fn checkout(branch: String) -> Result<()> {
-    // Try to checkout the branch
-    let result = git::exec("checkout", vec![&branch]);
+    if !branches::exists(&branch) {
+        if let Some(remote_branch) = branches::find_remote(&branch)? {
+            if branches::exists(remote_branch.local_name()) {
+                return fail!(
+                    "{}\nUse {} to checkout the local one",
+                    stylize(
+                        format!("Branch '{}' already exists locally", remote_branch.local_name()),
+                        "red"
+                    ),
+                    stylize(format!("br {}", remote_branch.local_name()), "yellow+italic")
+                );
+            }
 
-    match result {
-        Ok(output) => {
-            // Checkout succeeded
-            println!("{}", output);
-            Ok(())
+            return checkout_branch(&remote_branch);
+        }
+
+        if let Some(remote) = pick_remote(&branch)? {
+            return switch(&remote, vec!["--track", &remote]);
         }
+    }
+
+    if let Some(path) = worktree::find(&branch) {
+        return worktree::offer_cd(&branch, &path);
+    }
+
+    // Checkout the branch (or tag, or commit) if it exists
+    let commit = format!("{branch}^{{commit}}");
+    let result = git::exec("rev-parse", vec!["--verify", "-q", &commit]);
+
+    match result {
+        Ok(_) => switch(&branch, vec![&branch]),
         Err(_) => {
+            if let Err(reason) = check_branch_name(&branch) {
+                return fail!(
+                    "{}: {}",
+                    stylize(format!("Invalid branch name '{branch}'"), "red"),
+                    reason
+                );
+            }
+
             // Ask for confirmation to create new branch
             let ans = Confirm::new(&format!("Create a new branch '{}'?", &branch))
                 .with_default(true)
@@ -115,9 +248,33 @@ pub fn checkout(branch: String) -> Result<()> {
     }
 }
 
+/// The style of the prompts
+pub fn render_config() -> RenderConfig<'static> {
+    RenderConfig {
+        highlighted_option_prefix: Styled::new(">").with_fg(Color::LightBlue),
+        selected_checkbox: Styled::new("◉").with_fg(Color::LightBlue),
+        unselected_checkbox: Styled::new("○"),
+        selected_option: Some(
+            StyleSheet::new().with_attr(Attributes::ITALIC).with_fg(Color::LightBlue),
+        ),
+        answer: StyleSheet::new().with_attr(Attributes::ITALIC).with_fg(Color::LightBlue),
+        help_message: StyleSheet::new().with_fg(Color::White),
+        ..Default::default()
+    }
+}
+
+/// Turn the error of a prompt (e.g. canceled) into a styled one
+pub fn answer<T>(ans: InquireResult<T>) -> Result<T> {
+    match ans {
+        Ok(answer) => Ok(answer),
+        Err(err) => fail!("{}", stylize(err.to_string(), "red")),
+    }
+}
+
 pub fn list_branches() -> Result<()> {
     // Run `git branch --all --no-color`
     let output = git::exec("branch", vec!["--all", "--no-color"])?;
+    let descriptions = branches::descriptions();
     let mut current_branch = "".to_string();
 
     // Parse the output to get the list of branches
@@ -129,6 +286,10 @@ pub fn list_branches() -> Result<()> {
             current_branch = line.trim_start_matches("* ").to_string();
             continue;
         }
+        // skip symbolic refs such as `remotes/origin/HEAD -> origin/main`
+        if line.contains(" -> ") {
+            continue;
+        }
         let line = line.trim_start_matches("* ");
         branches.push(line.to_string());
     }
@@ -159,18 +320,46 @@ pub fn list_branches() -> Result<()> {
     println!(
         "{} {} {}",
         stylize("│", "blue+bold"),
-        stylize(current_branch, "blue+bold"),
+        stylize(&current_branch, "blue+bold"),
         stylize("(current)", "+dim")
     );
+    print_description(descriptions.get(&current_branch));
     for branch in branches {
         println!("{} {}", stylize("│", "blue+bold"), branch);
+        print_description(descriptions.get(&branch));
     }
     println!("{}", stylize("╰─", "blue+bold"));
 
     Ok(())
 }
 
+/// Print the description of a branch of the list, dimmed, below it
+fn print_description(description: Option<&String>) {
+    for line in description.into_iter().flat_map(|description| description.lines()) {
+        println!("{}   {}", stylize("│", "blue+bold"), stylize(line, "+dim"));
+    }
+}
+
+/// Edit the description of a branch (the current one if none is given) in the editor, with
+/// `git branch --edit-description`
+pub fn describe(branch: Option<String>) -> Result<()> {
+    let mut args = vec![s!("--edit-description")];
+
+    if let Some(branch) = branch {
+        if !branches::exists(&branch) {
+            return fail!("{}", stylize(format!("Branch '{branch}' does not exist"), "red"));
+        }
+        args.push(branch);
+    }
+
+    git::run("branch", args)
+}
+
 pub fn delete(branch: String) -> Result<()> {
+    if Protected::load()?.matches(&branch) {
+        return fail!("{}", stylize(format!("Branch '{branch}' is protected"), "red"));
+    }
+
     // Try to delete the branch
     let result = git::exec("branch", vec!["-d", &branch]);
 
@@ -180,6 +369,7 @@ pub fn delete(branch: String) -> Result<()> {
             println!("{}", output);
             Ok(())
         }
-        Err(_) => fail!("{}", stylize(format!("Branch {} does not exist.", branch), "red")),
+        // report git's error (not found, not fully merged, checked out...)
+        Err(err) => fail!("{}", stylize(err.to_string().trim(), "red")),
     }
 }
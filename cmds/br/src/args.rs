@@ -1,19 +1,36 @@
 use {
+    crate::prune::DEFAULT_STALE_DAYS,
     eyre::Result,
     lool::{cli::stylize::stylize, fail},
+    pico_args::Arguments,
 };
 
 pub enum Action {
     Version,
     Help,
-    List,
+    List {
+        tree: bool,
+        depth: Option<usize>,
+    },
     Checkout,
     CheckoutBranch(String),
     DeleteBranch(String),
+    Prune {
+        days: u64,
+        dry_run: bool,
+    },
+    New,
+    Rename {
+        from: Option<String>,
+        to: String,
+        copy: bool,
+    },
+    Describe(Option<String>),
+    Worktree(String),
 }
 
 pub fn parse_args() -> Result<Action> {
-    let mut pargs = pico_args::Arguments::from_env();
+    let mut pargs = Arguments::from_env();
 
     if pargs.contains(["-h", "--help"]) {
         return Ok(Action::Help);
@@ -24,13 +41,25 @@ pub fn parse_args() -> Result<Action> {
     }
 
     if pargs.contains(["-l", "--list"]) {
-        return Ok(Action::List);
+        return parse_list(pargs);
     }
 
     if let Ok(branch) = pargs.value_from_str(["-d", "--delete"]) {
         return Ok(Action::DeleteBranch(branch));
     }
 
+    if let Ok(Some(subcommand)) = pargs.subcommand() {
+        return match subcommand.as_str() {
+            "prune" => parse_prune(pargs),
+            "new" => parse_new(pargs),
+            "mv" => parse_rename(pargs, false),
+            "cp" => parse_rename(pargs, true),
+            "describe" => parse_describe(pargs),
+            "wt" => parse_worktree(pargs),
+            _ => Ok(Action::CheckoutBranch(subcommand)),
+        };
+    }
+
     if let Ok(branch) = pargs.free_from_str::<String>() {
         if branch.trim().starts_with('-') {
             return fail!(
@@ -57,3 +86,107 @@ pub fn parse_args() -> Result<Action> {
         Ok(Action::Checkout)
     }
 }
+
+/// `br --list [--tree] [--depth <n>]`
+fn parse_list(mut pargs: Arguments) -> Result<Action> {
+    let tree = pargs.contains(["-t", "--tree"]);
+    let depth = match pargs.opt_value_from_str("--depth") {
+        Ok(depth) => depth,
+        Err(_) => return bad_usage(),
+    };
+
+    if !pargs.finish().is_empty() {
+        return bad_usage();
+    }
+
+    Ok(Action::List {
+        // --depth implies --tree
+        tree: tree || depth.is_some(),
+        depth,
+    })
+}
+
+/// `br prune [--days <n>] [--dry-run]`
+fn parse_prune(mut pargs: Arguments) -> Result<Action> {
+    let dry_run = pargs.contains("--dry-run");
+    let days = match pargs.opt_value_from_str("--days") {
+        Ok(days) => days.unwrap_or(DEFAULT_STALE_DAYS),
+        Err(_) => return bad_usage(),
+    };
+
+    if !pargs.finish().is_empty() {
+        return bad_usage();
+    }
+
+    Ok(Action::Prune { days, dry_run })
+}
+
+/// `br new`
+fn parse_new(pargs: Arguments) -> Result<Action> {
+    if !pargs.finish().is_empty() {
+        return bad_usage();
+    }
+
+    Ok(Action::New)
+}
+
+/// `br mv [<old>] <new>` and `br cp [<src>] <dst>`
+fn parse_rename(pargs: Arguments, copy: bool) -> Result<Action> {
+    let mut names: Vec<String> =
+        pargs.finish().into_iter().filter_map(|arg| arg.into_string().ok()).collect();
+
+    if names.iter().any(|name| name.starts_with('-')) {
+        return bad_usage();
+    }
+
+    let to = match names.pop() {
+        Some(to) => to,
+        None => return bad_usage(),
+    };
+
+    match names.len() {
+        0 => Ok(Action::Rename {
+            from: None,
+            to,
+            copy,
+        }),
+        1 => Ok(Action::Rename {
+            from: names.pop(),
+            to,
+            copy,
+        }),
+        _ => bad_usage(),
+    }
+}
+
+/// `br describe [<branch>]`
+fn parse_describe(pargs: Arguments) -> Result<Action> {
+    let mut names: Vec<String> =
+        pargs.finish().into_iter().filter_map(|arg| arg.into_string().ok()).collect();
+
+    if names.len() > 1 || names.iter().any(|name| name.starts_with('-')) {
+        return bad_usage();
+    }
+
+    Ok(Action::Describe(names.pop()))
+}
+
+/// `br wt <branch>`
+fn parse_worktree(pargs: Arguments) -> Result<Action> {
+    let mut names: Vec<String> =
+        pargs.finish().into_iter().filter_map(|arg| arg.into_string().ok()).collect();
+
+    if names.len() != 1 || names[0].starts_with('-') {
+        return bad_usage();
+    }
+
+    Ok(Action::Worktree(names.remove(0)))
+}
+
+fn bad_usage<T>() -> Result<T> {
+    fail!(
+        "{}\nTry {} for more information",
+        stylize("Bad Usage", "red"),
+        stylize("br --help", "yellow+italic")
+    )
+}
@@ -0,0 +1,215 @@
+//! # br/branches
+//!
+//! Listing of the local and remote-tracking branches along with the metadata shown by the picker:
+//! last commit (date, author and subject), upstream and how far ahead/behind it the branch is.
+//!
+//! Local branches also carry their description (`branch.<name>.description`), read from the repo
+//! config with `GitConfig`.
+//!
+//! Symbolic refs (e.g. `origin/HEAD -> origin/main`) are never listed, and remote-tracking
+//! branches whose name already exists locally are left out, since the local one is the one to
+//! check out.
+
+use {
+    common::{
+        cli::context::ExecutionContext,
+        git::{self, Git},
+    },
+    eyre::Result,
+    lool::s,
+    std::collections::HashMap,
+};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Branch {
+    /// short name, e.g. `feat/login` or `origin/feat/login` for remote-tracking branches
+    pub name: String,
+    /// name of the remote of a remote-tracking branch, e.g. `origin`
+    pub remote: Option<String>,
+    pub current: bool,
+    /// relative date of the last commit, e.g. `3 days ago`
+    pub date: String,
+    /// date of the last commit as a unix timestamp
+    pub timestamp: i64,
+    pub author: String,
+    pub subject: String,
+    pub upstream: Option<String>,
+    /// the remote of the upstream, e.g. `origin`
+    pub upstream_remote: Option<String>,
+    pub ahead: usize,
+    pub behind: usize,
+    /// the upstream is configured but doesn't exist anymore
+    pub gone: bool,
+    /// `branch.<name>.description`
+    pub description: Option<String>,
+    /// path of the other worktree where the branch is checked out, if any
+    pub worktree: Option<String>,
+}
+
+impl Branch {
+    /// Parse a line of `git for-each-ref --format=<FORMAT>`
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split('\0');
+        let mut field = || fields.next();
+
+        let (refname, name, date, timestamp, author, subject) =
+            (field()?, field()?, field()?, field()?, field()?, field()?);
+        let (upstream, upstream_remote, track, head, symref, worktree) =
+            (field()?, field()?, field()?, field()?, field()?, field()?);
+
+        // skip symbolic refs such as `origin/HEAD`
+        if !symref.is_empty() {
+            return None;
+        }
+
+        let (ahead, behind, gone) = parse_track(track);
+        let remote = refname
+            .strip_prefix("refs/remotes/")
+            .and_then(|name| name.split_once('/'))
+            .map(|(remote, _)| s!(remote));
+
+        Some(Branch {
+            name: s!(name),
+            remote,
+            current: head == "*",
+            date: s!(date),
+            timestamp: timestamp.parse().unwrap_or(0),
+            author: s!(author),
+            subject: s!(subject),
+            upstream: Some(s!(upstream)).filter(|upstream| !upstream.is_empty()),
+            upstream_remote: Some(s!(upstream_remote)).filter(|remote| !remote.is_empty()),
+            ahead,
+            behind,
+            gone,
+            description: None,
+            // the current branch is the one checked out in this worktree
+            worktree: Some(s!(worktree)).filter(|path| !path.is_empty() && head != "*"),
+        })
+    }
+
+    /// The name of the branch without the remote, e.g. `feat/login` for `origin/feat/login`
+    pub fn local_name(&self) -> &str {
+        match &self.remote {
+            Some(remote) => self.name.strip_prefix(&format!("{remote}/")).unwrap_or(&self.name),
+            None => &self.name,
+        }
+    }
+
+    /// The name of the upstream branch in its remote, e.g. `feat/login` for `origin/feat/login`
+    pub fn upstream_branch(&self) -> Option<&str> {
+        let (upstream, remote) = (self.upstream.as_ref()?, self.upstream_remote.as_ref()?);
+        upstream.strip_prefix(&format!("{remote}/"))
+    }
+}
+
+const FORMAT: &str = "--format=%(refname)%00%(refname:short)%00%(committerdate:relative)%00\
+                      %(committerdate:unix)%00%(authorname)%00%(subject)%00%(upstream:short)%00\
+                      %(upstream:remotename)%00%(upstream:track,nobracket)%00%(HEAD)%00\
+                      %(symref)%00%(worktreepath)";
+
+/// List the local branches (and the remote-tracking ones if `all` is true), most recently
+/// committed first.
+pub fn list(all: bool) -> Result<Vec<Branch>> {
+    let mut args = vec!["--sort=-committerdate", FORMAT, "refs/heads"];
+    if all {
+        args.push("refs/remotes");
+    }
+
+    let output = git::exec("for-each-ref", args)?;
+    let mut branches: Vec<Branch> = output.lines().filter_map(Branch::parse).collect();
+
+    let mut descriptions = descriptions();
+    for branch in branches.iter_mut().filter(|branch| branch.remote.is_none()) {
+        branch.description = descriptions.remove(&branch.name);
+    }
+
+    // hide the remote-tracking branches that already exist locally
+    let local: Vec<String> =
+        branches.iter().filter(|branch| branch.remote.is_none()).map(|b| b.name.clone()).collect();
+
+    Ok(branches
+        .into_iter()
+        .filter(|branch| branch.remote.is_none() || !local.iter().any(|l| l == branch.local_name()))
+        .collect())
+}
+
+/// The descriptions of the local branches, by branch name
+pub fn descriptions() -> HashMap<String, String> {
+    ExecutionContext::new()
+        .and_then(|context| Git::new(&context))
+        .map(|git| git.config().get_branch_descriptions())
+        .unwrap_or_default()
+}
+
+/// List all the remote-tracking branches, most recently committed first
+pub fn remote() -> Result<Vec<Branch>> {
+    let output = git::exec("for-each-ref", vec!["--sort=-committerdate", FORMAT, "refs/remotes"])?;
+    Ok(output.lines().filter_map(Branch::parse).collect())
+}
+
+/// Whether a local branch with the given name exists
+pub fn exists(name: &str) -> bool {
+    git::exec("rev-parse", vec!["--verify", "-q", &format!("refs/heads/{name}")]).is_ok()
+}
+
+/// The default branch of the repository: the one `origin/HEAD` points at, or else `main` or
+/// `master`, or else `init.defaultBranch`. The local branch is preferred, falling back to the
+/// remote-tracking one (e.g. `origin/main`) if there is no local branch with that name.
+pub fn default_branch() -> Option<String> {
+    if let Ok(origin_head) =
+        git::exec("symbolic-ref", vec!["-q", "--short", "refs/remotes/origin/HEAD"])
+    {
+        let origin_head = origin_head.trim();
+        let name = origin_head.trim_start_matches("origin/");
+
+        return Some(s!(if exists(name) { name } else { origin_head }));
+    }
+
+    if let Some(name) = ["main", "master"].into_iter().find(|name| exists(name)) {
+        return Some(s!(name));
+    }
+
+    git::exec("config", vec!["--get", "init.defaultBranch"])
+        .ok()
+        .map(|name| s!(name.trim()))
+        .filter(|name| exists(name))
+}
+
+/// Find a remote-tracking branch by its short name (e.g. `origin/feat/login`)
+pub fn find_remote(name: &str) -> Result<Option<Branch>> {
+    let remote_ref = format!("refs/remotes/{name}");
+    let output = git::exec("for-each-ref", vec![FORMAT, &remote_ref])?;
+
+    Ok(output.lines().filter_map(Branch::parse).find(|branch| branch.name == name))
+}
+
+/// The remote-tracking branches named `name` in any remote (e.g. `origin/feat/login` and
+/// `upstream/feat/login` for `feat/login`)
+pub fn on_remotes(name: &str) -> Result<Vec<Branch>> {
+    let output = git::exec("for-each-ref", vec![FORMAT, "refs/remotes"])?;
+
+    Ok(output
+        .lines()
+        .filter_map(Branch::parse)
+        .filter(|branch| branch.local_name() == name)
+        .collect())
+}
+
+/// Parse `%(upstream:track,nobracket)`, e.g. `ahead 1, behind 2` or `gone`
+fn parse_track(track: &str) -> (usize, usize, bool) {
+    if track == "gone" {
+        return (0, 0, true);
+    }
+
+    let (mut ahead, mut behind) = (0, 0);
+
+    for part in track.split(", ") {
+        match part.split_once(' ') {
+            Some(("ahead", count)) => ahead = count.parse().unwrap_or(0),
+            Some(("behind", count)) => behind = count.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+
+    (ahead, behind, false)
+}
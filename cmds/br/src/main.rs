@@ -4,16 +4,69 @@
 //!
 //! It is a wrapper around `git branch` and `git checkout` commands.
 //!
-//! - When you call `br` without any arguments, it will list all the branches in the repository in a
-//! Select prompt where you can navigate with up and down arrows and select a branch with the Enter
-//! key. When selecting a branch, it will perform a `git checkout <branch>` command.
+//! - When you call `br` without any arguments, it will list the local branches in the repository in
+//! a Select prompt where you can navigate with up and down arrows, type to fuzzy-filter them by
+//! name and select a branch with the Enter key. Branches are sorted by recency (last commit date)
+//! and show their last commit (date, author and subject), upstream and how far ahead/behind it
+//! they are. The Tab key toggles between local branches only and all branches (including the
+//! remote-tracking ones), which are listed right away if there are no other local branches, and
+//! the Esc key cancels. When selecting a branch, it will perform a `git checkout <branch>`
+//! command, or `git checkout --track <remote>/<branch>` for remote-tracking branches so a local
+//! branch tracking it is created. Remote-tracking branches that already exist locally and symbolic
+//! refs (`origin/HEAD`) are not listed.
 //!
 //! - If you call `br` with a branch name, like `br my-branch`, it will first check if the branch
-//! exists. If it does, it will perform a `git checkout <branch>` command. If it doesn't, it will
+//! exists. If it does, it will perform a `git checkout <branch>` command. If it's a remote-tracking
+//! branch (`br origin/my-branch`) or it exists in a remote, it will create a local branch tracking
+//! it (asking which remote to track if it exists in several ones). If it doesn't, it will
 //! perform a `git checkout -b <branch>` command and create a new branch (asking first for your
 //! confirmation).
 //!
+//! - Before switching branches, `br` checks for local changes, and for untracked files that the
+//! target branch also has. If there are any, it shows the files the checkout would overwrite and
+//! lets you stash the changes (untracked files included) and re-apply them after switching, carry
+//! them over with `git checkout --merge` (unless untracked files are in the way), or cancel.
+//!
+//! - Branches checked out in another worktree are marked with the path of the worktree
+//! (`⌂ <path>`) in the Select prompt and in `br --list --tree`. Selecting one (or calling
+//! `br <branch>`) offers to go to that worktree: the path is written to the file in the
+//! `BR_CD_FILE` env var, for a shell wrapper to `cd` there, or printed if it isn't set.
+//!
+//! - If you call `br wt <branch>`, it will create a worktree for the branch (tracking the remote
+//! branch or creating a new one if it doesn't exist locally) in the `gitools.worktreesDir` git
+//! config directory, `../<repo>.worktrees` by default, and go there like above.
+//!
 //! - If you call `br --list or -l`, it will list all the branches in the repository in a Select
+//!
+//! - If you call `br --list --tree` (or `-l -t`), it will group the branches by the `/` segments of
+//! their names, showing how many branches each group has and marking the current branch and the
+//! ones whose upstream is gone or that have unpushed commits. `--depth <n>` collapses the groups
+//! deeper than `n` levels.
+//!
+//! - If you call `br new`, it will ask for the type of the branch (feat/fix/chore/release), a
+//! ticket id and a description, build the branch name from the `gitools.branchTemplate` git config
+//! (`{type}/{ticket}-{slug}` by default), validate it with git's ref name rules and create it from
+//! the current HEAD, the default branch or a remote branch.
+//!
+//! - If you call `br mv [<old>] <new>` or `br cp [<src>] <dst>`, it will rename or copy the branch
+//! (the current one if only one name is given) along with its `branch.<name>.*` config, offering
+//! to push the new name and delete the old remote branch unless it's protected (or, for a copy, to
+//! push it or unset the upstream copied from the source branch). The commands are shown as a plan
+//! before running them.
+//!
+//! - If you call `br describe [<branch>]`, it will open the description of the branch (the current
+//! one if none is given) in your editor, with `git branch --edit-description`. Descriptions are
+//! shown dimmed below the branch in `br --list` and in the Select prompt.
+//!
+//! - If you call `br prune`, it will list the local branches that are merged into the default
+//! branch, whose upstream is gone or that haven't been touched in `--days` days (90 by default)
+//! and let you select which ones to delete, offering to delete their remote branches too. With
+//! `--dry-run` it only lists them. The current branch, the default branch and the protected ones
+//! are never deleted.
+//!
+//! - Protected branches (`main`, `master` and the patterns in the `gitools.protected` git config or
+//! in `branches.protected` of the gitools `config.toml`) can't be deleted with `br -d` or
+//! `br prune`.
 
 use {
     args::{parse_args, Action},
@@ -23,6 +76,15 @@ use {
 
 mod action;
 mod args;
+mod branches;
+mod new;
+mod picker;
+mod prune;
+mod refname;
+mod rename;
+mod switch;
+mod tree;
+mod worktree;
 
 const COMMAND: &str = "br";
 
@@ -32,7 +94,13 @@ fn main() -> Result<()> {
         Action::Help => print::tldr(COMMAND),
         Action::Checkout => action::select_checkout(),
         Action::CheckoutBranch(branch) => action::checkout(branch),
-        Action::List => action::list_branches(),
+        Action::List { tree: false, .. } => action::list_branches(),
+        Action::List { tree: true, depth } => tree::list_branches(depth),
         Action::DeleteBranch(branch) => action::delete(branch),
+        Action::Prune { days, dry_run } => prune::prune(days, dry_run),
+        Action::New => new::new(),
+        Action::Rename { from, to, copy } => rename::rename(from, to, copy),
+        Action::Describe(branch) => action::describe(branch),
+        Action::Worktree(branch) => worktree::add(branch),
     }
 }
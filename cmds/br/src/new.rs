@@ -0,0 +1,175 @@
+//! # br/new
+//!
+//! `br new` creates a branch following the team's naming conventions: it asks for the type of the
+//! branch, a ticket id (optional) and a description, builds the name from a template and lets the
+//! user choose where the branch starts from.
+//!
+//! The template is read from the `gitools.branchTemplate` git config and defaults to
+//! `{type}/{ticket}-{slug}`, where `{slug}` is the description in kebab-case. When the ticket is
+//! left empty, it is dropped along with its separator (e.g. `feat/login-page`).
+
+use {
+    crate::{
+        action::{answer, render_config},
+        branches,
+        refname::check_branch_name,
+    },
+    common::git,
+    eyre::Result,
+    inquire::{validator::Validation, Select, Text},
+    lool::{cli::stylize::stylize, fail, s},
+    std::fmt::{self, Display},
+};
+
+const DEFAULT_TEMPLATE: &str = "{type}/{ticket}-{slug}";
+const TYPES: [&str; 4] = ["feat", "fix", "chore", "release"];
+
+/// Where the new branch starts from
+enum StartPoint {
+    Head(String),
+    Default(String),
+    Remote,
+}
+
+impl Display for StartPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartPoint::Head(head) => write!(f, "current HEAD {}", stylize(head, "+dim")),
+            StartPoint::Default(branch) => write!(f, "default branch {}", stylize(branch, "+dim")),
+            StartPoint::Remote => write!(f, "a remote branch..."),
+        }
+    }
+}
+
+pub fn new() -> Result<()> {
+    let template = git::exec("config", vec!["--get", "gitools.branchTemplate"])
+        .map(|template| s!(template.trim()))
+        .unwrap_or_else(|_| s!(DEFAULT_TEMPLATE));
+
+    let kind =
+        answer(Select::new("Type", TYPES.to_vec()).with_render_config(render_config()).prompt())?;
+
+    let ticket = answer(
+        Text::new("Ticket")
+            .with_help_message("leave empty for none")
+            .with_render_config(render_config())
+            .prompt(),
+    )?;
+
+    let description = answer(
+        Text::new("Description")
+            .with_validator(|input: &str| {
+                Ok(match slugify(input).is_empty() {
+                    true => Validation::Invalid("The description can't be empty".into()),
+                    false => Validation::Valid,
+                })
+            })
+            .with_render_config(render_config())
+            .prompt(),
+    )?;
+
+    let name = render(&template, kind, ticket.trim(), &slugify(&description));
+
+    if let Err(reason) = check_branch_name(&name) {
+        return fail!("{}: {}", stylize(format!("Invalid branch name '{name}'"), "red"), reason);
+    }
+
+    if branches::exists(&name) {
+        return fail!("{}", stylize(format!("Branch '{name}' already exists"), "red"));
+    }
+
+    println!("{} {}", stylize("Branch:", "+dim"), stylize(&name, "bright-blue+bold"));
+
+    let start = match select_start_point()? {
+        StartPoint::Head(_) => None,
+        StartPoint::Default(branch) => Some(branch),
+        StartPoint::Remote => Some(select_remote_branch()?),
+    };
+
+    let mut args = vec!["-b", name.as_str()];
+    if let Some(start) = &start {
+        // don't track the start point: the new branch gets its own upstream when pushed
+        args.extend(["--no-track", start.as_str()]);
+    }
+
+    let output = git::exec("checkout", args)?;
+    print!("{}", output);
+    println!("{}", stylize(format!("Switched to a new branch '{name}'"), "green"));
+
+    Ok(())
+}
+
+fn select_start_point() -> Result<StartPoint> {
+    let head = git::exec("symbolic-ref", vec!["-q", "--short", "HEAD"])
+        .map(|head| s!(head.trim()))
+        .unwrap_or_else(|_| s!("(detached)"));
+
+    let mut options = vec![StartPoint::Head(head.clone())];
+    if let Some(default) = branches::default_branch().filter(|default| *default != head) {
+        options.push(StartPoint::Default(default));
+    }
+    options.push(StartPoint::Remote);
+
+    answer(Select::new("Start from", options).with_render_config(render_config()).prompt())
+}
+
+fn select_remote_branch() -> Result<String> {
+    let remote_branches: Vec<String> =
+        branches::remote()?.into_iter().map(|branch| branch.name).collect();
+
+    if remote_branches.is_empty() {
+        return fail!("{}", stylize("No remote branches found", "red"));
+    }
+
+    answer(
+        Select::new("Remote branch", remote_branches)
+            .with_page_size(10)
+            .with_render_config(render_config())
+            .prompt(),
+    )
+}
+
+/// Turn a description into kebab-case: lowercase alphanumeric words separated by `-`
+pub fn slugify(description: &str) -> String {
+    description
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Build the branch name from the template. If there is no ticket, the `{ticket}` placeholder is
+/// removed along with one of its separators.
+pub fn render(template: &str, kind: &str, ticket: &str, slug: &str) -> String {
+    let mut template = s!(template);
+
+    if ticket.is_empty() {
+        for separator in ['-', '_', '/'] {
+            template = template
+                .replace(&format!("{{ticket}}{separator}"), "")
+                .replace(&format!("{separator}{{ticket}}"), "");
+        }
+    }
+
+    template.replace("{type}", kind).replace("{ticket}", ticket).replace("{slug}", slug)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, slugify, DEFAULT_TEMPLATE};
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Add the login page!"), "add-the-login-page");
+        assert_eq!(slugify("  fix: crash on   startup "), "fix-crash-on-startup");
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(render(DEFAULT_TEMPLATE, "feat", "ABC-123", "login"), "feat/ABC-123-login");
+        assert_eq!(render(DEFAULT_TEMPLATE, "feat", "", "login"), "feat/login");
+        assert_eq!(render("{ticket}/{type}/{slug}", "fix", "", "crash"), "fix/crash");
+        assert_eq!(render("{type}/{slug}_{ticket}", "chore", "42", "deps"), "chore/deps_42");
+    }
+}
@@ -0,0 +1,295 @@
+//! # br/picker
+//!
+//! A fuzzy-filtered select prompt for the branch picker. It looks and filters like inquire's
+//! `Select` (with the same scorer), but inquire doesn't let prompts handle keys of their own, and
+//! the picker needs one to switch between local and all branches: pressing `Tab` returns
+//! [`Picked::Toggle`].
+
+use {
+    crossterm::{
+        cursor::{Hide, MoveToColumn, MoveUp, Show},
+        event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+        execute, queue,
+        style::Print,
+        terminal::{self, Clear, ClearType},
+    },
+    eyre::Result,
+    inquire::Select,
+    lool::{cli::stylize::stylize, fail},
+    std::{
+        fmt::Display,
+        io::{stderr, stdin, IsTerminal, Write},
+    },
+};
+
+/// How many options are shown at once
+const PAGE_SIZE: usize = 10;
+
+/// What was picked
+pub enum Picked<T> {
+    Option(T),
+    Toggle,
+    Cancel,
+}
+
+/// Restores the terminal when the prompt ends, however it ends
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(stderr(), Hide)?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = execute!(stderr(), Show);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct State<'a, T> {
+    message: &'a str,
+    help: &'a str,
+    options: Vec<T>,
+    name: &'a dyn Fn(&T) -> &str,
+    input: String,
+    /// indexes of the options matching the input, best match first
+    filtered: Vec<usize>,
+    cursor: usize,
+    offset: usize,
+    /// lines of the last render, to clear them before rendering again
+    drawn: u16,
+}
+
+impl<'a, T: Display> State<'a, T> {
+    fn filter(&mut self) {
+        self.filtered = filter(&self.options, self.name, &self.input);
+        self.cursor = 0;
+        self.offset = 0;
+    }
+
+    fn move_cursor(&mut self, up: bool, by: usize) {
+        let len = self.filtered.len();
+        if len == 0 {
+            return;
+        }
+
+        // single steps wrap around like inquire does, pages stop at the ends
+        self.cursor = match (up, by) {
+            (true, 1) => (self.cursor + len - 1) % len,
+            (false, 1) => (self.cursor + 1) % len,
+            (true, _) => self.cursor.saturating_sub(by),
+            (false, _) => (self.cursor + by).min(len - 1),
+        };
+
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.cursor >= self.offset + PAGE_SIZE {
+            self.offset = self.cursor + 1 - PAGE_SIZE;
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("{} {} {}", stylize("?", "green"), self.message, self.input)];
+
+        if self.filtered.is_empty() {
+            lines.push(stylize("  No matches", "+dim"));
+        }
+
+        let page = self.filtered.iter().enumerate().skip(self.offset).take(PAGE_SIZE);
+        for (i, &option) in page {
+            let prefix = match i == self.cursor {
+                true => stylize(">", "bright-blue"),
+                false => String::from(" "),
+            };
+
+            let option = self.options[option].to_string();
+            for (n, line) in option.lines().enumerate() {
+                match n {
+                    0 => lines.push(format!("{prefix} {line}")),
+                    _ => lines.push(format!("  {line}")),
+                }
+            }
+        }
+
+        lines.push(stylize(format!("[{}]", self.help), "white"));
+        lines
+    }
+
+    fn clear(&mut self, out: &mut impl Write) -> Result<()> {
+        queue!(out, MoveToColumn(0))?;
+        if self.drawn > 1 {
+            queue!(out, MoveUp(self.drawn - 1))?;
+        }
+        queue!(out, Clear(ClearType::FromCursorDown))?;
+        self.drawn = 0;
+        Ok(())
+    }
+
+    fn render(&mut self, out: &mut impl Write) -> Result<()> {
+        let width = terminal::size().map(|(width, _)| width as usize).unwrap_or(80);
+        let lines: Vec<String> = self.lines().iter().map(|line| fit(line, width - 1)).collect();
+
+        self.clear(out)?;
+        queue!(out, Print(lines.join("\r\n")))?;
+        out.flush()?;
+
+        self.drawn = lines.len() as u16;
+        Ok(())
+    }
+}
+
+/// Show the options, filtered by the name `name` gives for each one as the user types, until one
+/// is selected (Enter), the prompt is canceled (Esc) or `Tab` is pressed
+pub fn pick<T: Display>(
+    message: &str,
+    help: &str,
+    options: Vec<T>,
+    name: &dyn Fn(&T) -> &str,
+) -> Result<Picked<T>> {
+    if !stdin().is_terminal() {
+        return fail!("{}", stylize("The input device is not a TTY", "red"));
+    }
+
+    let mut state = State {
+        message,
+        help,
+        options,
+        name,
+        input: String::new(),
+        filtered: vec![],
+        cursor: 0,
+        offset: 0,
+        drawn: 0,
+    };
+    state.filter();
+
+    let mut out = stderr();
+    let raw = RawMode::enable()?;
+
+    let picked = loop {
+        state.render(&mut out)?;
+
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        else {
+            continue;
+        };
+
+        match (code, modifiers) {
+            (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
+                state.clear(&mut out)?;
+                drop(raw);
+                return fail!("{}", stylize("Operation interrupted by ctrl-c", "red"));
+            }
+            (KeyCode::Esc, _) => break None,
+            (KeyCode::Tab, _) => break Some(Picked::Toggle),
+            (KeyCode::Enter, _) => match state.filtered.get(state.cursor) {
+                Some(&option) => break Some(Picked::Option(option)),
+                None => continue,
+            },
+            (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
+                state.move_cursor(true, 1)
+            }
+            (KeyCode::Down, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) => {
+                state.move_cursor(false, 1)
+            }
+            (KeyCode::PageUp, _) => state.move_cursor(true, PAGE_SIZE),
+            (KeyCode::PageDown, _) => state.move_cursor(false, PAGE_SIZE),
+            (KeyCode::Backspace, _) if !state.input.is_empty() => {
+                state.input.pop();
+                state.filter();
+            }
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                state.input.push(c);
+                state.filter();
+            }
+            _ => {}
+        }
+    };
+
+    state.clear(&mut out)?;
+    drop(raw);
+
+    Ok(match picked {
+        Some(Picked::Option(option)) => {
+            let option = state.options.swap_remove(option);
+            let answer = stylize((state.name)(&option), "bright-blue+italic");
+            eprintln!("{} {} {}", stylize("?", "green"), state.message, answer);
+            Picked::Option(option)
+        }
+        Some(Picked::Toggle) => Picked::Toggle,
+        _ => Picked::Cancel,
+    })
+}
+
+/// The indexes of the options whose name matches the input, best match first
+fn filter<T: Display>(options: &[T], name: &dyn Fn(&T) -> &str, input: &str) -> Vec<usize> {
+    let mut scored: Vec<(usize, i64)> = options
+        .iter()
+        .enumerate()
+        .filter_map(|(i, option)| {
+            Select::<T>::DEFAULT_SCORER(input, option, name(option), i).map(|score| (i, score))
+        })
+        .collect();
+
+    scored.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+    scored.into_iter().map(|(i, _)| i).collect()
+}
+
+/// Cut a line to `width` columns, not counting (nor cutting) its ANSI escape codes
+fn fit(line: &str, width: usize) -> String {
+    let mut fitted = String::new();
+    let mut columns = 0;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            fitted.push(c);
+            for c in chars.by_ref() {
+                fitted.push(c);
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else if columns < width {
+            fitted.push(c);
+            columns += 1;
+        } else {
+            // reset the style in case the cut left one open
+            fitted.push_str("\x1b[0m");
+            break;
+        }
+    }
+
+    fitted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{filter, fit};
+
+    #[test]
+    fn test_filter() {
+        let options = ["main", "feat/login", "fix/logout", "origin/feat/login"].map(String::from);
+        let name = &String::as_str;
+
+        assert_eq!(filter(&options, name, ""), [0, 1, 2, 3]);
+        assert_eq!(filter(&options, name, "login"), [1, 3]);
+        assert!(filter(&options, name, "zzz").is_empty());
+    }
+
+    #[test]
+    fn test_fit() {
+        assert_eq!(fit("feat/login", 20), "feat/login");
+        assert_eq!(fit("feat/login", 4), "feat\x1b[0m");
+        assert_eq!(fit("\x1b[35mfeat/login\x1b[0m", 4), "\x1b[35mfeat\x1b[0m");
+    }
+}
@@ -0,0 +1,295 @@
+//! # br/prune
+//!
+//! `br prune` lists the local branches that are probably safe to delete:
+//!
+//! - branches merged into the default branch
+//! - branches whose upstream is gone (deleted in the remote)
+//! - branches that haven't been touched (committed to) in a given number of days
+//!
+//! and lets the user select which ones to delete, offering to delete their remote branches too.
+//! The current branch, the default branch and the protected ones are never listed.
+
+use {
+    crate::{
+        action::render_config,
+        branches::{self, Branch},
+    },
+    common::{git, protected::Protected},
+    eyre::Result,
+    inquire::{Confirm, MultiSelect},
+    lool::{cli::stylize::stylize, fail, s},
+    std::{
+        collections::BTreeMap,
+        fmt::{self, Display},
+        time::{SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// Branches not committed to in this number of days are considered stale
+pub const DEFAULT_STALE_DAYS: u64 = 90;
+
+const DAY: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reason {
+    Merged,
+    Gone,
+    /// untouched for this number of days
+    Stale(i64),
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Merged => write!(f, "merged"),
+            Reason::Gone => write!(f, "upstream gone"),
+            Reason::Stale(days) => write!(f, "untouched for {days} days"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub branch: Branch,
+    pub reasons: Vec<Reason>,
+}
+
+impl Candidate {
+    fn merged(&self) -> bool {
+        self.reasons.contains(&Reason::Merged)
+    }
+
+    /// The git command that deletes the branch: `-d` if merged, `-D` otherwise
+    fn delete_args(&self) -> Vec<&str> {
+        vec![if self.merged() { "-d" } else { "-D" }, &self.branch.name]
+    }
+
+    /// The remote and name of the remote branch, if it still exists
+    fn remote_branch(&self) -> Option<(&str, &str)> {
+        if self.branch.gone {
+            return None;
+        }
+
+        Some((self.branch.upstream_remote.as_deref()?, self.branch.upstream_branch()?))
+    }
+}
+
+impl Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reasons: Vec<String> = self.reasons.iter().map(|reason| reason.to_string()).collect();
+        let unmerged = if self.merged() {
+            s!("")
+        } else {
+            stylize(" (not merged)", "red")
+        };
+
+        write!(
+            f,
+            "{} {}{} {}",
+            self.branch.name,
+            stylize(reasons.join(", "), "yellow"),
+            unmerged,
+            stylize(format!("· {}", self.branch.date), "+dim")
+        )
+    }
+}
+
+/// Select the local branches that can be pruned, along with the reasons why. The current branch,
+/// the default one and the protected ones are left out.
+pub fn candidates(
+    branches: Vec<Branch>,
+    merged: &[String],
+    default: Option<&str>,
+    protected: &Protected,
+    now: i64,
+    days: u64,
+) -> Vec<Candidate> {
+    branches
+        .into_iter()
+        .filter(|branch| branch.remote.is_none() && !branch.current)
+        .filter(|branch| Some(branch.name.as_str()) != default && !protected.matches(&branch.name))
+        .filter_map(|branch| {
+            let mut reasons = Vec::new();
+
+            if merged.contains(&branch.name) {
+                reasons.push(Reason::Merged);
+            }
+
+            if branch.gone {
+                reasons.push(Reason::Gone);
+            }
+
+            let untouched = (now - branch.timestamp) / DAY;
+            if untouched >= days as i64 {
+                reasons.push(Reason::Stale(untouched));
+            }
+
+            (!reasons.is_empty()).then_some(Candidate { branch, reasons })
+        })
+        .collect()
+}
+
+pub fn prune(days: u64, dry_run: bool) -> Result<()> {
+    let default = branches::default_branch();
+    let protected = Protected::load()?;
+
+    let merged = match &default {
+        Some(default) => {
+            git::exec("branch", vec!["--merged", default, "--format=%(refname:short)"])?
+                .lines()
+                .map(|line| s!(line.trim()))
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+    let candidates =
+        candidates(branches::list(false)?, &merged, default.as_deref(), &protected, now, days);
+
+    if candidates.is_empty() {
+        println!("{}", stylize("Nothing to prune", "green"));
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("These branches can be pruned:\n");
+        for candidate in &candidates {
+            println!("{}{}", stylize("  - ", "green"), candidate);
+        }
+        println!("\n{}", stylize("Dry run, nothing was deleted", "+dim"));
+        return Ok(());
+    }
+
+    let defaults: Vec<usize> = candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, candidate)| candidate.merged())
+        .map(|(i, _)| i)
+        .collect();
+
+    let selected = MultiSelect::new("Select the branches to delete", candidates)
+        .with_page_size(10)
+        .with_default(&defaults)
+        .with_formatter(&|selected| {
+            selected.iter().map(|c| c.value.branch.name.clone()).collect::<Vec<_>>().join(", ")
+        })
+        .with_render_config(render_config())
+        .prompt();
+
+    let selected = match selected {
+        Ok(selected) => selected,
+        Err(err) => return fail!("{}", stylize(err.to_string(), "red")),
+    };
+
+    if selected.is_empty() {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let mut failed = false;
+    let mut deleted = Vec::new();
+
+    for candidate in &selected {
+        match git::exec("branch", candidate.delete_args()) {
+            Ok(output) => {
+                print!("{}", output);
+                deleted.push(candidate);
+            }
+            Err(err) => {
+                failed = true;
+                eprintln!("{}", stylize(err.to_string().trim(), "red"));
+            }
+        }
+    }
+
+    // remote → branches to delete in it
+    let mut remote_branches: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (remote, branch) in deleted.into_iter().filter_map(Candidate::remote_branch) {
+        if !protected.matches(branch) {
+            remote_branches.entry(remote).or_default().push(branch);
+        }
+    }
+
+    if !remote_branches.is_empty() {
+        println!("\nThese branches still exist in their remotes:\n");
+        for (remote, branches) in &remote_branches {
+            for branch in branches {
+                println!("{}{}/{}", stylize("  - ", "yellow"), remote, branch);
+            }
+        }
+        println!();
+
+        let ans = Confirm::new("Delete them from the remotes too?").with_default(false).prompt();
+
+        match ans {
+            Ok(true) => {
+                for (remote, branches) in remote_branches {
+                    let mut args = vec![s!(remote), s!("--delete")];
+                    args.extend(branches.into_iter().map(String::from));
+
+                    if git::run("push", args).is_err() {
+                        failed = true;
+                    }
+                }
+            }
+            Ok(false) => {}
+            Err(err) => return fail!("{}", stylize(err.to_string(), "red")),
+        }
+    }
+
+    if failed {
+        return fail!("{}", stylize("Some branches could not be deleted", "red"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{candidates, Reason, DAY},
+        crate::branches::Branch,
+        common::protected::Protected,
+        lool::s,
+    };
+
+    fn branch(name: &str, timestamp: i64, gone: bool) -> Branch {
+        Branch {
+            name: s!(name),
+            timestamp,
+            gone,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_candidates() {
+        let now = 1000 * DAY;
+        let branches = vec![
+            branch("main", 0, false),
+            branch("release/1.0", 0, false),
+            branch("feat/merged", now, false),
+            branch("feat/gone", now, true),
+            branch("feat/old", now - 100 * DAY, false),
+            branch("feat/active", now, false),
+        ];
+        let merged = vec![s!("main"), s!("release/1.0"), s!("feat/merged")];
+        let protected = Protected::from_patterns(["main", "release/*"]);
+
+        let candidates = candidates(branches, &merged, Some("main"), &protected, now, 90);
+        let candidates: Vec<(&str, Vec<Reason>)> = candidates
+            .iter()
+            .map(|candidate| (candidate.branch.name.as_str(), candidate.reasons.clone()))
+            .collect();
+
+        assert_eq!(
+            candidates,
+            vec![
+                ("feat/merged", vec![Reason::Merged]),
+                ("feat/gone", vec![Reason::Gone]),
+                ("feat/old", vec![Reason::Stale(100)]),
+            ]
+        );
+    }
+}
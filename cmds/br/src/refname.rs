@@ -0,0 +1,113 @@
+//! # br/refname
+//!
+//! Validation of branch names following the rules of `git check-ref-format --branch`, so invalid
+//! names are rejected before running git.
+
+/// Check whether `name` is a valid branch name, returning why it isn't otherwise
+pub fn check_branch_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("it's empty".into());
+    }
+
+    if name.starts_with('-') {
+        return Err("it can't begin with '-'".into());
+    }
+
+    if name == "HEAD" {
+        return Err("'HEAD' is not a valid branch name".into());
+    }
+
+    check_ref_format(name)
+}
+
+/// The rules of `git check-ref-format` (with `--allow-onelevel`, as branch names are checked
+/// without the `refs/heads/` prefix)
+fn check_ref_format(name: &str) -> Result<(), String> {
+    if name == "@" {
+        return Err("it can't be '@'".into());
+    }
+
+    if name.starts_with('/') || name.ends_with('/') {
+        return Err("it can't begin or end with '/'".into());
+    }
+
+    if name.ends_with('.') {
+        return Err("it can't end with '.'".into());
+    }
+
+    if name.contains("..") {
+        return Err("it can't contain '..'".into());
+    }
+
+    if name.contains("@{") {
+        return Err("it can't contain '@{'".into());
+    }
+
+    if let Some(c) = name.chars().find(|c| c.is_ascii_control()) {
+        return Err(format!("it can't contain control characters ({:?})", c));
+    }
+
+    if let Some(c) = name.chars().find(|c| " ~^:?*[\\".contains(*c)) {
+        return Err(format!("it can't contain '{c}'"));
+    }
+
+    for component in name.split('/') {
+        if component.is_empty() {
+            return Err("it can't contain '//'".into());
+        }
+
+        if component.starts_with('.') {
+            return Err(format!("'{component}' can't begin with '.'"));
+        }
+
+        if component.ends_with(".lock") {
+            return Err(format!("'{component}' can't end with '.lock'"));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_branch_name;
+
+    #[test]
+    fn test_valid_branch_names() {
+        for name in ["main", "feat/ABC-123-login-page", "release/1.0", "fix/a.b", "user@host", "ñ"]
+        {
+            assert_eq!(check_branch_name(name), Ok(()), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_branch_names() {
+        for name in [
+            "",
+            "-feat",
+            "HEAD",
+            "@",
+            "/feat",
+            "feat/",
+            "feat.",
+            "feat//login",
+            "feat/.login",
+            "feat.lock",
+            "feat/login.lock/x",
+            "a..b",
+            "a@{1}",
+            "a b",
+            "a~1",
+            "a^",
+            "a:b",
+            "a?",
+            "a*",
+            "a[b",
+            "a\\b",
+            "a\tb",
+            "a\u{7f}",
+        ] {
+            assert!(check_branch_name(name).is_err(), "{name:?}");
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! # br/rename
+//!
+//! `br mv [<old>] <new>` and `br cp [<src>] <dst>` rename or copy a local branch (the current one
+//! if only one name is given). `git branch -m` and `git branch -c` move or copy the
+//! `branch.<name>.*` config along with the branch, so the rest of the work is fixing up the
+//! remote side:
+//!
+//! - rename: optionally push the new name (setting it as the upstream) and delete the old remote
+//!   branch, so no stale upstream is left behind; protected branches (see [`common::protected`])
+//!   are never deleted
+//! - copy: optionally push the copy (setting it as its upstream); otherwise the upstream copied
+//!   from the source branch is unset, so the copy doesn't track someone else's branch
+//!
+//! All the commands are shown as a plan and executed after confirmation.
+
+use {
+    crate::{
+        action::{answer, render_config},
+        branches,
+        refname::check_branch_name,
+    },
+    common::{git, protected::Protected},
+    eyre::Result,
+    inquire::Confirm,
+    lool::{cli::stylize::stylize, fail, s},
+};
+
+pub fn rename(from: Option<String>, to: String, copy: bool) -> Result<()> {
+    let from = match from {
+        Some(from) => from,
+        None => match git::exec("symbolic-ref", vec!["-q", "--short", "HEAD"]) {
+            Ok(head) => s!(head.trim()),
+            Err(_) => return fail!("{}", stylize("Not on a branch (detached HEAD)", "red")),
+        },
+    };
+
+    let Some(branch) = branches::list(false)?.into_iter().find(|branch| branch.name == from) else {
+        return fail!("{}", stylize(format!("Branch '{from}' does not exist"), "red"));
+    };
+
+    if let Err(reason) = check_branch_name(&to) {
+        return fail!("{}: {}", stylize(format!("Invalid branch name '{to}'"), "red"), reason);
+    }
+
+    if branches::exists(&to) {
+        return fail!("{}", stylize(format!("Branch '{to}' already exists"), "red"));
+    }
+
+    let mut plan: Vec<(&str, Vec<String>)> =
+        vec![("branch", vec![s!(if copy { "-c" } else { "-m" }), from.clone(), to.clone()])];
+
+    if let (Some(remote), Some(upstream)) = (&branch.upstream_remote, branch.upstream_branch()) {
+        let push = answer(
+            Confirm::new(&format!("Push '{to}' to {remote} and set it as its upstream?"))
+                .with_default(!copy)
+                .with_render_config(render_config())
+                .prompt(),
+        )?;
+
+        if push {
+            plan.push(("push", vec![s!("-u"), remote.clone(), to.clone()]));
+        } else if copy {
+            plan.push(("branch", vec![s!("--unset-upstream"), to.clone()]));
+        }
+
+        if push && !copy && !branch.gone {
+            let protected = Protected::load()?;
+
+            if protected.matches(&from) || protected.matches(upstream) {
+                println!(
+                    "{}",
+                    stylize(
+                        format!("{remote}/{upstream} is protected, it won't be deleted"),
+                        "yellow"
+                    )
+                );
+            } else {
+                let delete = answer(
+                    Confirm::new(&format!("Delete the old remote branch {remote}/{upstream}?"))
+                        .with_default(true)
+                        .with_render_config(render_config())
+                        .prompt(),
+                )?;
+
+                if delete {
+                    plan.push(("push", vec![remote.clone(), s!("--delete"), s!(upstream)]));
+                }
+            }
+        }
+    }
+
+    println!("\nWill execute the following commands: \n");
+    for (cmd, args) in &plan {
+        println!("{}git {} {}", stylize("  - ", "green"), cmd, args.join(" "));
+    }
+    println!();
+
+    let confirmed = answer(
+        Confirm::new("Do you want to continue?")
+            .with_default(true)
+            .with_render_config(render_config())
+            .prompt(),
+    )?;
+
+    if !confirmed {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    for (cmd, args) in plan {
+        let command = format!("git {} {}", cmd, args.join(" "));
+
+        if git::run(cmd, args).is_err() {
+            return fail!("{}", stylize(format!("Failed to execute: {command}"), "red"));
+        }
+    }
+
+    println!(
+        "{}",
+        stylize(
+            format!("{} '{from}' to '{to}'", if copy { "Copied" } else { "Renamed" }),
+            "green"
+        )
+    );
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+//! # br/switch
+//!
+//! Checkout that takes care of local changes. Before switching, the working tree is checked for
+//! changes to tracked files and for untracked files that the target also has (git refuses to
+//! overwrite them); if there are any, the files that the checkout would overwrite (the changed
+//! files that also differ in the target, and those untracked ones) are shown, and the user can
+//! choose to:
+//!
+//! - stash the changes (including the untracked files), switch and re-apply them
+//! - carry them over with `git checkout --merge`, if no untracked file is in the way
+//! - cancel
+
+use {
+    crate::action::{answer, render_config},
+    common::git,
+    eyre::Result,
+    inquire::Select,
+    lool::{cli::stylize::stylize, fail},
+    std::{
+        collections::HashSet,
+        env,
+        fmt::{self, Display},
+    },
+};
+
+#[derive(Clone, Copy)]
+enum Choice {
+    Stash,
+    Merge,
+    Cancel,
+}
+
+impl Display for Choice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Choice::Stash => write!(f, "Stash the changes and re-apply them after switching"),
+            Choice::Merge => write!(f, "Carry the changes over (git checkout --merge)"),
+            Choice::Cancel => write!(f, "Cancel"),
+        }
+    }
+}
+
+/// Run `git checkout <args>`, `target` being the commit it switches to (e.g. `origin/feat` for
+/// `--track origin/feat`)
+pub fn switch(target: &str, args: Vec<&str>) -> Result<()> {
+    let changed = changed_files();
+    let untracked = untracked_files(target);
+
+    if changed.is_empty() && untracked.is_empty() {
+        return checkout(args);
+    }
+
+    let overwritten = overwritten_files(&changed, target);
+
+    println!(
+        "{}",
+        stylize(
+            format!("You have local changes in {} file(s)", changed.len() + untracked.len()),
+            "yellow"
+        )
+    );
+
+    if overwritten.is_empty() && untracked.is_empty() {
+        println!("{}\n", stylize("None of them would be overwritten by the checkout", "+dim"));
+    } else {
+        println!("These files would be overwritten by the checkout:\n");
+        for file in &overwritten {
+            println!("{}{}", stylize("  - ", "red"), file);
+        }
+        for file in &untracked {
+            println!("{}{} {}", stylize("  - ", "red"), file, stylize("(untracked)", "+dim"));
+        }
+        println!();
+    }
+
+    // `--merge` doesn't carry untracked files over, git still refuses to overwrite them
+    let choices = match untracked.is_empty() {
+        true => vec![Choice::Stash, Choice::Merge, Choice::Cancel],
+        false => vec![Choice::Stash, Choice::Cancel],
+    };
+
+    let choice = answer(
+        Select::new("What do you want to do?", choices)
+            .without_help_message()
+            .with_render_config(render_config())
+            .prompt(),
+    )?;
+
+    match choice {
+        Choice::Stash => stash_and_switch(target, args, &changed, &untracked),
+        Choice::Merge => checkout([vec!["--merge"], args].concat()),
+        Choice::Cancel => {
+            println!("Aborted.");
+            Ok(())
+        }
+    }
+}
+
+/// Stash the changes, switch and re-apply them. The untracked files in the way are stashed along
+/// with the changed files, leaving the other untracked files where they are.
+fn stash_and_switch(
+    target: &str,
+    args: Vec<&str>,
+    changed: &[String],
+    untracked: &[String],
+) -> Result<()> {
+    let message = format!("br: switching to {target}");
+
+    let paths: Vec<String> =
+        changed.iter().chain(untracked).map(|file| format!(":/{file}")).collect();
+    let mut push = vec!["push", "-m", &message];
+    if !untracked.is_empty() {
+        // stashing them can remove the directory we're in, if it only had untracked files
+        let top = git::exec("rev-parse", vec!["--show-toplevel"])?;
+        env::set_current_dir(top.trim())?;
+
+        push.push("--include-untracked");
+        push.push("--");
+        push.extend(paths.iter().map(String::as_str));
+    }
+    git::exec("stash", push)?;
+
+    if let Err(err) = checkout(args) {
+        // put the changes back where they were
+        git::exec("stash", vec!["pop"])?;
+        return Err(err);
+    }
+
+    if git::exec("stash", vec!["pop"]).is_err() {
+        return fail!(
+            "{}\nResolve the conflicts; the changes are kept in the stash ({})",
+            stylize("The changes could not be re-applied cleanly", "red"),
+            stylize(message, "yellow")
+        );
+    }
+
+    println!("{}", stylize("Local changes re-applied", "green"));
+    Ok(())
+}
+
+fn checkout(args: Vec<&str>) -> Result<()> {
+    match git::exec("checkout", args) {
+        Ok(output) => {
+            print!("{}", output);
+            Ok(())
+        }
+        Err(err) => fail!("{}", stylize(err.to_string().trim(), "red")),
+    }
+}
+
+/// The tracked files with changes (staged or not)
+fn changed_files() -> Vec<String> {
+    git::exec("diff", vec!["--name-only", "HEAD", "--"])
+        .map(|output| output.lines().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// The changed files that differ between HEAD and the target, which the checkout would overwrite
+fn overwritten_files(changed: &[String], target: &str) -> Vec<String> {
+    let Ok(output) = git::exec("diff", vec!["--name-only", "HEAD", target, "--"]) else {
+        return Vec::new();
+    };
+
+    output.lines().filter(|file| changed.iter().any(|c| c == file)).map(String::from).collect()
+}
+
+/// The untracked (and not ignored) files that the target also has, which git refuses to overwrite
+fn untracked_files(target: &str) -> Vec<String> {
+    let untracked =
+        git::exec("ls-files", vec!["--others", "--exclude-standard", "--full-name", ":/"])
+            .unwrap_or_default();
+    if untracked.is_empty() {
+        return Vec::new();
+    }
+
+    let Ok(files) = git::exec("ls-tree", vec!["-r", "--name-only", "--full-tree", target]) else {
+        return Vec::new();
+    };
+    let files: HashSet<&str> = files.lines().collect();
+
+    untracked.lines().filter(|file| files.contains(file)).map(String::from).collect()
+}
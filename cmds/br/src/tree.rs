@@ -0,0 +1,182 @@
+//! # br/tree
+//!
+//! Tree view of the branches for `br --list --tree`: branch names are split by `/` and grouped,
+//! showing how many branches each group has. Groups deeper than `--depth` are collapsed to their
+//! count. Remote-tracking branches are grouped under `remotes/<remote>`.
+//!
+//! Local branches are marked when they are the current one, when their upstream is gone and when
+//! they have commits that aren't pushed (no upstream, or ahead of it), and with the path of the
+//! worktree where they are checked out if it isn't this one.
+
+use {
+    crate::branches::{self, Branch},
+    eyre::Result,
+    lool::{cli::stylize::stylize, s},
+    std::collections::BTreeMap,
+};
+
+#[derive(Debug, Default)]
+pub struct Node {
+    pub branch: Option<Branch>,
+    pub children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    /// Build the tree from the branches, grouping them by the `/` segments of their names
+    pub fn from_branches(branches: Vec<Branch>) -> Self {
+        let mut root = Node::default();
+
+        for branch in branches {
+            let path = match &branch.remote {
+                Some(_) => format!("remotes/{}", branch.name),
+                None => branch.name.clone(),
+            };
+
+            let mut node = &mut root;
+            for segment in path.split('/') {
+                node = node.children.entry(s!(segment)).or_default();
+            }
+            node.branch = Some(branch);
+        }
+
+        root
+    }
+
+    /// Number of branches in the subtree
+    pub fn count(&self) -> usize {
+        self.branch.iter().count() + self.children.values().map(Node::count).sum::<usize>()
+    }
+
+    fn is_group(&self) -> bool {
+        !self.children.is_empty()
+    }
+
+    /// The children of the node: branches first, then groups, both alphabetically
+    fn sorted_children(&self) -> Vec<(&String, &Node)> {
+        let (mut children, groups): (Vec<_>, Vec<_>) =
+            self.children.iter().partition(|(_, node)| !node.is_group());
+        children.extend(groups);
+        children
+    }
+}
+
+pub fn list_branches(depth: Option<usize>) -> Result<()> {
+    let mut all = branches::list(false)?;
+    all.extend(branches::remote()?);
+
+    let root = Node::from_branches(all);
+    let bar = stylize("│", "blue+bold");
+
+    println!("{}", stylize("╭─", "blue+bold"));
+    print_children(&root, &bar, "", 0, depth);
+    println!("{}", stylize("╰─", "blue+bold"));
+
+    Ok(())
+}
+
+/// Print the children of a node, `indent` being the tree guides of the ancestors
+fn print_children(node: &Node, bar: &str, indent: &str, level: usize, depth: Option<usize>) {
+    let children = node.sorted_children();
+
+    for (i, (name, child)) in children.iter().enumerate() {
+        let last = i == children.len() - 1;
+
+        let connector = match (level, last) {
+            (0, _) => "",
+            (_, true) => "└─ ",
+            (_, false) => "├─ ",
+        };
+        // the guides for the children of this node
+        let child_indent = match (level, last) {
+            (0, _) => s!(indent),
+            (_, true) => format!("{indent}   "),
+            (_, false) => format!("{indent}│  "),
+        };
+        let guides = stylize(format!("{indent}{connector}"), "blue");
+
+        if child.is_group() {
+            let collapsed = depth.is_some_and(|depth| level + 1 >= depth);
+            let count = stylize(format!("({})", child.count()), "+dim");
+
+            if collapsed {
+                println!("{bar} {guides}{}/… {count}", stylize(name.as_str(), "bold"));
+            } else {
+                println!("{bar} {guides}{}/ {count}", stylize(name.as_str(), "bold"));
+                print_children(child, bar, &child_indent, level + 1, depth);
+            }
+        } else if let Some(branch) = &child.branch {
+            println!("{bar} {guides}{}", label(name, branch));
+
+            let description = branch.description.iter().flat_map(|d| d.lines());
+            for line in description {
+                let guides = stylize(format!("{child_indent}  "), "blue");
+                println!("{bar} {guides}{}", stylize(line, "+dim"));
+            }
+        }
+    }
+}
+
+/// The name of a branch along with its markers
+fn label(name: &str, branch: &Branch) -> String {
+    let mut label = if branch.current {
+        stylize(name, "blue+bold")
+    } else {
+        s!(name)
+    };
+
+    if branch.remote.is_some() {
+        return label;
+    }
+
+    if branch.current {
+        label.push_str(&format!(" {}", stylize("(current)", "+dim")));
+    }
+
+    if let Some(path) = &branch.worktree {
+        label.push_str(&format!(" {}", stylize(format!("⌂ {path}"), "blue")));
+    }
+
+    if branch.gone {
+        label.push_str(&format!(" {}", stylize("✗ upstream gone", "red")));
+    } else if branch.upstream.is_none() {
+        label.push_str(&format!(" {}", stylize("↑ not pushed", "yellow")));
+    } else if branch.ahead > 0 {
+        label.push_str(&format!(" {}", stylize(format!("↑{} unpushed", branch.ahead), "yellow")));
+    }
+
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::Node, crate::branches::Branch, lool::s};
+
+    fn branch(name: &str, remote: Option<&str>) -> Branch {
+        Branch {
+            name: s!(name),
+            remote: remote.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tree_groups_and_counts() {
+        let root = Node::from_branches(vec![
+            branch("main", None),
+            branch("feature/login", None),
+            branch("feature/search/v2", None),
+            branch("release/1.0", None),
+            branch("origin/main", Some("origin")),
+            branch("origin/feature/login", Some("origin")),
+        ]);
+
+        assert_eq!(root.count(), 6);
+        assert_eq!(root.children["feature"].count(), 2);
+        assert_eq!(root.children["feature"].children["search"].count(), 1);
+        assert_eq!(root.children["remotes"].children["origin"].count(), 2);
+
+        let order: Vec<&str> =
+            root.sorted_children().into_iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(order, vec!["main", "feature", "release", "remotes"]);
+    }
+}
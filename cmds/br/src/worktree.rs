@@ -0,0 +1,155 @@
+//! # br/worktree
+//!
+//! A branch can only be checked out in one worktree at a time, so instead of failing with git's
+//! error, `br` offers to go to the worktree where the branch is checked out. A process can't
+//! change the directory of its parent shell, so the path is written to the file in `BR_CD_FILE`
+//! (for a shell wrapper to `cd` there) or printed otherwise.
+//!
+//! `br wt <branch>` creates a worktree for a branch in the worktrees directory: the
+//! `gitools.worktreesDir` git config (relative to the main worktree) or `../<repo>.worktrees` by
+//! default, so worktrees live next to the repository instead of inside it.
+
+use {
+    crate::{
+        action::{answer, pick_remote, render_config},
+        branches,
+        refname::check_branch_name,
+    },
+    common::git,
+    eyre::Result,
+    inquire::Confirm,
+    lool::{cli::stylize::stylize, fail, s},
+    std::{env, fs, path::PathBuf},
+};
+
+const CD_FILE_ENV: &str = "BR_CD_FILE";
+
+/// The path of the other worktree where `branch` is checked out, if any
+pub fn find(branch: &str) -> Option<String> {
+    let refname = format!("refs/heads/{branch}");
+    let path = git::exec("for-each-ref", vec!["--format=%(worktreepath)", &refname]).ok()?;
+    let path = path.trim();
+
+    let toplevel = git::exec("rev-parse", vec!["--show-toplevel"]).unwrap_or_default();
+
+    match path.is_empty() || path == toplevel.trim() {
+        true => None,
+        false => Some(s!(path)),
+    }
+}
+
+/// Tell the user that `branch` is checked out in another worktree and offer to go there
+pub fn offer_cd(branch: &str, path: &str) -> Result<()> {
+    println!(
+        "{} {}",
+        stylize(format!("'{branch}' is checked out in another worktree:"), "yellow"),
+        stylize(path, "+bold")
+    );
+
+    let go = answer(
+        Confirm::new("Go there?").with_default(true).with_render_config(render_config()).prompt(),
+    )?;
+
+    if go {
+        cd(path)?;
+    }
+
+    Ok(())
+}
+
+/// Hand the path over to the shell wrapper, or print it if there is none
+fn cd(path: &str) -> Result<()> {
+    match env::var(CD_FILE_ENV) {
+        Ok(file) if !file.is_empty() => fs::write(file, path)?,
+        _ => println!("{} {}", stylize("Run:", "+dim"), stylize(format!("cd {path}"), "cyan")),
+    }
+
+    Ok(())
+}
+
+/// `br wt <branch>`: create a worktree for the branch, creating the branch too if needed
+pub fn add(branch: String) -> Result<()> {
+    if let Some(path) = find(&branch) {
+        return offer_cd(&branch, &path);
+    }
+
+    let head = git::exec("symbolic-ref", vec!["-q", "--short", "HEAD"]).unwrap_or_default();
+    if head.trim() == branch {
+        return fail!("{}", stylize(format!("'{branch}' is checked out here"), "red"));
+    }
+
+    let path = worktrees_dir()?.join(dir_name(&branch));
+    let path = path.to_string_lossy().to_string();
+
+    if PathBuf::from(&path).exists() {
+        return fail!("{}", stylize(format!("'{path}' already exists"), "red"));
+    }
+
+    let args = if branches::exists(&branch) {
+        vec![s!("add"), path.clone(), branch.clone()]
+    } else if let Some(remote) = pick_remote(&branch)? {
+        vec![s!("add"), s!("--track"), s!("-b"), branch.clone(), path.clone(), remote]
+    } else {
+        if let Err(reason) = check_branch_name(&branch) {
+            return fail!(
+                "{}: {}",
+                stylize(format!("Invalid branch name '{branch}'"), "red"),
+                reason
+            );
+        }
+
+        let create = answer(
+            Confirm::new(&format!("Create a new branch '{branch}'?"))
+                .with_default(true)
+                .with_render_config(render_config())
+                .prompt(),
+        )?;
+
+        if !create {
+            println!("Aborted.");
+            return Ok(());
+        }
+
+        vec![s!("add"), s!("-b"), branch.clone(), path.clone()]
+    };
+
+    if let Err(err) = git::exec("worktree", args.iter().map(String::as_str).collect()) {
+        return fail!("{}", stylize(err.to_string().trim(), "red"));
+    }
+
+    println!("{}", stylize(format!("Created a worktree for '{branch}' in {path}"), "green"));
+    cd(&path)
+}
+
+/// The directory where `br wt` creates the worktrees
+fn worktrees_dir() -> Result<PathBuf> {
+    let common_dir = git::exec("rev-parse", vec!["--path-format=absolute", "--git-common-dir"])?;
+
+    // the main worktree is the one holding the git dir
+    let Some(main) = PathBuf::from(common_dir.trim()).parent().map(PathBuf::from) else {
+        return fail!("{}", stylize("Could not find the main worktree", "red"));
+    };
+
+    if let Ok(dir) = git::exec("config", vec!["--get", "gitools.worktreesDir"]) {
+        return Ok(main.join(dir.trim()));
+    }
+
+    let name = main.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    Ok(main.with_file_name(format!("{name}.worktrees")))
+}
+
+/// The directory name of the worktree of a branch, flattening the `/` groups
+fn dir_name(branch: &str) -> String {
+    branch.replace('/', "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dir_name;
+
+    #[test]
+    fn test_dir_name() {
+        assert_eq!(dir_name("main"), "main");
+        assert_eq!(dir_name("feat/ABC-123-login"), "feat-ABC-123-login");
+    }
+}
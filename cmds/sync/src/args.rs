@@ -0,0 +1,116 @@
+use {
+    eyre::Result,
+    lool::{cli::stylize::stylize, fail},
+    std::ffi::OsString,
+};
+
+/// What to do with an interrupted plan
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resume {
+    /// `--continue`: resume the plan after the failed step
+    Continue,
+    /// `--abort`: undo the plan
+    Abort,
+}
+
+/// Arguments passed to the `sync` command.
+///
+/// The positional arguments (`from <remote[:branch]> [to <remote>]`) are kept as they are so they
+/// can be validated by `check_args` and turned into a `Plan`; the options are extracted from the
+/// list, no matter where they were placed.
+pub struct Args {
+    pub positional: Vec<String>,
+    /// `--branches <pattern>`: sync every local branch matching the glob pattern
+    pub branches: Option<String>,
+    /// `--all-tracking`: sync every local branch tracking a branch on the source remote
+    pub all_tracking: bool,
+    /// `--tags`: also fetch the tags from the source remote and push them to the destination
+    pub tags: bool,
+    /// `--notes`: also fetch `refs/notes/*` from the source remote and push them to the destination
+    pub notes: bool,
+    /// `--yes` (`-y`): don't ask for confirmation before executing the plan
+    pub yes: bool,
+    /// `--dry-run`: print the plan and exit without executing it
+    pub dry_run: bool,
+    /// `--json`: print the plan and the result of each step as JSON
+    pub json: bool,
+    /// `--continue` or `--abort` an interrupted plan
+    pub resume: Option<Resume>,
+}
+
+impl Args {
+    /// name of the source remote (`from <remote[:branch]>`)
+    pub fn remote_from(&self) -> &str {
+        self.positional[1].split(':').next().unwrap_or_default()
+    }
+
+    /// name of the branch given with `from <remote>:<branch>`, if any
+    pub fn branch(&self) -> Option<&str> {
+        self.positional[1].split_once(':').map(|(_, branch)| branch)
+    }
+
+    /// name of the destination remote (`to <remote>`), if any
+    pub fn remote_to(&self) -> Option<&str> {
+        self.positional.get(3).map(String::as_str)
+    }
+}
+
+pub fn parse(args: Vec<String>) -> Result<Args> {
+    let mut pargs = pico_args::Arguments::from_vec(args.into_iter().map(OsString::from).collect());
+
+    let all_tracking = pargs.contains("--all-tracking");
+    let tags = pargs.contains("--tags");
+    let notes = pargs.contains("--notes");
+    let yes = pargs.contains(["-y", "--yes"]);
+    let dry_run = pargs.contains("--dry-run");
+    let json = pargs.contains("--json");
+    let resume = match (pargs.contains("--continue"), pargs.contains("--abort")) {
+        (true, true) => {
+            return fail!(
+                "{}\n{} and {} can't be used together",
+                stylize("Bad Usage", "red"),
+                stylize("--continue", "yellow"),
+                stylize("--abort", "yellow")
+            )
+        }
+        (true, false) => Some(Resume::Continue),
+        (false, true) => Some(Resume::Abort),
+        (false, false) => None,
+    };
+    let branches: Option<String> = pargs.opt_value_from_str("--branches")?;
+
+    if all_tracking && branches.is_some() {
+        return fail!(
+            "{}\n{} and {} can't be used together",
+            stylize("Bad Usage", "red"),
+            stylize("--branches", "yellow"),
+            stylize("--all-tracking", "yellow")
+        );
+    }
+
+    // the JSON output can't be mixed with the confirmation prompt
+    if json && !yes && !dry_run {
+        return fail!(
+            "{}\n{} requires {} or {}",
+            stylize("Bad Usage", "red"),
+            stylize("--json", "yellow"),
+            stylize("--yes", "yellow"),
+            stylize("--dry-run", "yellow")
+        );
+    }
+
+    let positional =
+        pargs.finish().into_iter().map(|arg| arg.to_string_lossy().to_string()).collect();
+
+    Ok(Args {
+        positional,
+        branches,
+        all_tracking,
+        tags,
+        notes,
+        yes,
+        dry_run,
+        json,
+        resume,
+    })
+}
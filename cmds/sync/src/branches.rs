@@ -0,0 +1,175 @@
+//! # sync/branches
+//!
+//! Inspection of the local branches that take part in a multi-branch sync (`--branches <pattern>`
+//! or `--all-tracking`). Each selected branch is compared against its counterpart in the source
+//! remote so the plan knows whether it can be fast-forwarded, pushed as is, or has to be left
+//! alone because both sides diverged.
+
+use {
+    common::git::Git,
+    eyre::Result,
+    glob::Pattern,
+    lool::{cli::stylize::stylize, fail, s},
+    serde::Serialize,
+};
+
+/// How the local branches to sync are selected
+pub enum Selector {
+    /// every local branch whose name matches the glob pattern (e.g. `release/*`)
+    Pattern(Pattern),
+    /// every local branch tracking a branch of the source remote
+    AllTracking,
+}
+
+impl Selector {
+    pub fn from_args(branches: Option<&str>, all_tracking: bool) -> Result<Option<Self>> {
+        if all_tracking {
+            return Ok(Some(Selector::AllTracking));
+        }
+
+        match branches {
+            Some(pattern) => match Pattern::new(pattern) {
+                Ok(pattern) => Ok(Some(Selector::Pattern(pattern))),
+                Err(err) => fail!("Invalid branch pattern '{pattern}': {err}"),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+/// Relation between a local branch and its counterpart in the remote
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Relation {
+    UpToDate,
+    /// the local branch is behind by N commits and can be fast-forwarded
+    Behind(usize),
+    /// the local branch is ahead by N commits, there is nothing to get from the remote
+    Ahead(usize),
+    /// both sides have commits the other one doesn't have (ahead, behind)
+    Diverged(usize, usize),
+}
+
+/// A local branch and the state of its counterpart in the source remote
+#[derive(Serialize, Debug, Clone)]
+pub struct BranchState {
+    pub name: String,
+    pub sha: String,
+    /// short name of the remote-tracking branch, e.g. `origin/release/1.0`
+    pub counterpart: String,
+    pub counterpart_sha: String,
+    pub relation: Relation,
+    /// whether the branch is the one checked out in the working tree
+    pub current: bool,
+}
+
+impl BranchState {
+    /// one line description of the relation, used in the plan summary
+    pub fn describe(&self) -> String {
+        match self.relation {
+            Relation::UpToDate => s!("up to date"),
+            Relation::Behind(n) => format!("behind by {n}"),
+            Relation::Ahead(n) => format!("ahead by {n}"),
+            Relation::Diverged(ahead, behind) => {
+                format!("diverged (ahead {ahead}, behind {behind})")
+            }
+        }
+    }
+
+    pub fn print(&self, prefix: Option<&str>) {
+        let color = match self.relation {
+            Relation::Diverged(..) => "yellow",
+            _ => "green",
+        };
+
+        println!(
+            "{}{} {} {} {}",
+            prefix.unwrap_or(""),
+            stylize(&self.name, "bright-blue"),
+            stylize("→", "+dim"),
+            stylize(&self.counterpart, "magenta"),
+            stylize(self.describe(), color)
+        );
+    }
+}
+
+/// Collect the local branches selected by `selector` which have a counterpart in `remote`.
+///
+/// This should be called after fetching `remote`, otherwise the comparison is made against stale
+/// remote-tracking branches.
+pub fn collect(
+    git: &Git,
+    remote: &str,
+    selector: &Selector,
+    current: Option<&str>,
+) -> Result<Vec<BranchState>> {
+    let output = git.exec_and_get_result(
+        "for-each-ref",
+        &[
+            "--format=%(refname:short)%00%(objectname)%00%(upstream)%00%(upstream:remotename)",
+            "refs/heads",
+        ],
+    )?;
+
+    let mut states = Vec::new();
+
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split('\0').collect();
+        if fields.len() < 4 {
+            continue;
+        }
+
+        let (name, sha, upstream, upstream_remote) = (fields[0], fields[1], fields[2], fields[3]);
+
+        let counterpart_ref = match selector {
+            Selector::Pattern(pattern) if pattern.matches(name) => {
+                format!("refs/remotes/{remote}/{name}")
+            }
+            Selector::AllTracking if upstream_remote == remote && !upstream.is_empty() => {
+                s!(upstream)
+            }
+            _ => continue,
+        };
+
+        // skip branches that don't exist in the remote
+        let Ok(counterpart_sha) =
+            git.exec_and_get_result("rev-parse", &["--verify", "-q", &counterpart_ref])
+        else {
+            continue;
+        };
+        let counterpart_sha = s!(counterpart_sha.trim());
+
+        let relation = relation(git, sha, &counterpart_sha)?;
+
+        states.push(BranchState {
+            name: s!(name),
+            sha: s!(sha),
+            counterpart: s!(counterpart_ref.trim_start_matches("refs/remotes/")),
+            counterpart_sha,
+            relation,
+            current: current == Some(name),
+        });
+    }
+
+    Ok(states)
+}
+
+/// Compare two commits using `git rev-list --left-right --count`
+fn relation(git: &Git, local: &str, remote: &str) -> Result<Relation> {
+    if local == remote {
+        return Ok(Relation::UpToDate);
+    }
+
+    let range = format!("{local}...{remote}");
+    let output = git.exec_and_get_result("rev-list", &["--left-right", "--count", &range])?;
+    let counts: Vec<usize> =
+        output.split_whitespace().filter_map(|count| count.parse().ok()).collect();
+
+    match counts.as_slice() {
+        [0, 0] => Ok(Relation::UpToDate),
+        [0, behind] => Ok(Relation::Behind(*behind)),
+        [ahead, 0] => Ok(Relation::Ahead(*ahead)),
+        [ahead, behind] => Ok(Relation::Diverged(*ahead, *behind)),
+        _ => fail!("Unexpected output from git rev-list: {}", output.trim()),
+    }
+}
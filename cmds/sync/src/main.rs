@@ -73,15 +73,65 @@
 //!   - `git rebase github/feature/branch`
 //!   - `git push gitea feature/branch`
 //!
+//! **Case**: Sync several branches at once
+//!
+//! `sync from origin --branches 'release/*'` or `sync from origin --all-tracking`
+//!
+//! **where**:
+//!   - `--branches <pattern>` selects every local branch matching the glob pattern
+//!   - `--all-tracking` selects every local branch tracking a branch of `origin`
+//!
+//! **and will**:
+//!   - `git fetch origin` (before showing the plan)
+//!   - `git update-ref refs/heads/<branch> <new> <old>` for each branch that is behind (or
+//!     `git rebase origin/<branch>` if it's the current branch)
+//!   - `git push <remote> <branches...>` if `to <remote>` was given
+//!
+//! Branches that diverged from their counterpart are reported and left untouched.
+//!
+//! **Case**: Mirror tags and notes from `gitea` to `github`
+//!
+//! `sync from gitea to github --tags --notes`
+//!
+//! **and will** (besides the usual fetch, rebase and push):
+//!   - `git fetch gitea --no-tags refs/tags/<tag>:refs/tags/<tag>...` for the tags missing locally
+//!   - `git push github refs/tags/<tag>...` for the tags missing in `github`
+//!   - `git fetch gitea refs/notes/*:refs/notes/*` and `git push github refs/notes/*:refs/notes/*`
+//!
+//! Conflicting tags (same name, different object) are shown before anything is executed and are
+//! never fetched or pushed.
+//!
 //! ## Note:
 //!
 //! Before running all the commands, this program will show you the commands that will be executed
 //! and ask for your confirmation. If you don't confirm, the program will exit without running any
 //! command.
+//!
+//! When syncing a single branch, the remotes are fetched first and the plan comes with a preview
+//! of the branch: how far ahead/behind the source it is, the incoming commits, the local commits
+//! that will be rebased (and the ones dropped because they are already upstream) and the commits
+//! that will be pushed to the destination, warning if the push would be rejected or if it pushes
+//! rebased history to a protected branch.
+//!
+//! If a command fails (e.g. a rebase conflict), the plan is kept in `.git/gitools/sync-state` so
+//! it can be resumed with `sync --continue` or undone with `sync --abort`, the same way
+//! `git rebase --continue` and `git rebase --abort` work.
+//!
+//! For scripts, `--yes` skips the confirmation, `--dry-run` only prints the plan and `--json`
+//! prints the plan and the result of each command (exit code, duration, stdout and stderr) as
+//! JSON. The confirmation is refused if stdin is not a terminal.
 
+mod args;
+mod branches;
 mod plan;
+mod preview;
+mod report;
+mod state;
+mod tags;
 
 use {
+    args::{Args, Resume},
+    branches::{Relation, Selector},
     common::{
         cli::{
             action::{self, Action::*},
@@ -91,9 +141,13 @@ use {
         git::{Git, Head},
     },
     eyre::{Ok, Result},
-    lool::{cli::stylize::stylize, fail},
+    lool::{cli::stylize::stylize, fail, s},
     plan::Plan,
-    std::io::{self, Write},
+    preview::Preview,
+    report::{Report, StepResult},
+    state::{Snapshot, State},
+    std::io::{self, IsTerminal, Write},
+    tags::TagSync,
 };
 
 const COMMAND: &str = "sync";
@@ -103,12 +157,28 @@ fn main() -> Result<()> {
         ShowTldr { name } => print::tldr(&name),
         ShowVersion => print::version(COMMAND, env!("CARGO_PKG_VERSION")),
         Cmd { cmd: _, args } => {
-            check_args(&args)?;
+            let args = args::parse(args)?;
+
+            if let Some(resume) = args.resume {
+                let git = Git::new(&ExecutionContext::new()?)?;
+                return handle_resume(resume, &args, &git);
+            }
+
+            check_args(&args.positional)?;
+            let selector = Selector::from_args(args.branches.as_deref(), args.all_tracking)?;
             let git = Git::new(&ExecutionContext::new()?)?;
+            state::ensure_no_state(&git)?;
 
-            match git.get_head() {
-                Head::Branch(branch) => handle(args, branch, &git),
-                Head::Commit(commit) => {
+            match (git.get_head(), selector) {
+                (head, Some(selector)) => {
+                    let current = match head {
+                        Head::Branch(branch) => Some(branch),
+                        Head::Commit(_) => None,
+                    };
+                    handle_branches(&args, selector, current, &git)
+                }
+                (Head::Branch(branch), None) => handle(&args, branch, &git),
+                (Head::Commit(commit), None) => {
                     fail!("You are in a detached HEAD state at commit {}", commit)
                 }
             }
@@ -116,35 +186,299 @@ fn main() -> Result<()> {
     }
 }
 
-fn handle(args: Vec<String>, branch: String, git: &Git) -> Result<()> {
-    let plan = Plan::from_args(args, branch, Some(git.config().get_remotes()))?;
+fn handle(args: &Args, branch: String, git: &Git) -> Result<()> {
+    let remotes = Some(git.config().get_remotes());
+    let mut plan = Plan::from_args(args.positional.clone(), branch.clone(), remotes, false)?;
 
-    println!("Will execute the following commands: \n");
-    plan.print(Some(&stylize("  - ", "green")));
+    // fetch first (instead of as part of the plan), so the preview shows what's about to be
+    // rebased and pushed
+    let (remote_from, remote_to) = (args.remote_from(), args.remote_to());
+    fetch(args, git, remote_from)?;
+    if let Some(remote) = remote_to {
+        fetch(args, git, remote)?;
+    }
 
-    if confirm("Do you want to continue?").is_ok() {
-        println!("\n{}\n", stylize("Executing commands...", "cyan"));
+    let branch = args.branch().unwrap_or(&branch);
+    let preview = Preview::collect(git, branch, remote_from, remote_to)?;
 
-        let commands = plan.commands();
-        for (i, (cmd, args)) in commands.iter().enumerate() {
-            git.exec(cmd, args)?;
+    if !args.json {
+        println!();
+        preview.print();
+    }
 
-            if i < commands.len() - 1 {
-                println!();
-            }
+    let mut report = Report {
+        preview: Some(preview),
+        ..Default::default()
+    };
+    add_refs(&mut plan, &mut report, args, git)?;
+
+    let state = State::new(plan.clone(), git)?;
+    execute(&plan, report, Some(state), args, git)
+}
+
+/// Sync several local branches at once (`--branches <pattern>` or `--all-tracking`).
+///
+/// The source remote is fetched before building the plan, since the plan depends on how each
+/// branch relates to its counterpart in the remote.
+fn handle_branches(
+    args: &Args,
+    selector: Selector,
+    current: Option<String>,
+    git: &Git,
+) -> Result<()> {
+    let remote_from = &args.positional[1];
+    let remote_to = args.remote_to();
+
+    if remote_from.contains(':') {
+        return fail!(
+            "{}\nA branch can't be specified along with {} or {}",
+            stylize("Bad Usage", "red"),
+            stylize("--branches", "yellow"),
+            stylize("--all-tracking", "yellow")
+        );
+    }
+
+    plan::check_remotes(&git.config().get_remotes(), remote_from, remote_to)?;
+
+    fetch(args, git, remote_from)?;
+
+    let branches = branches::collect(git, remote_from, &selector, current.as_deref())?;
+
+    if branches.is_empty() {
+        return fail!("No local branch to sync with '{}'", remote_from);
+    }
+
+    if !args.json {
+        println!("\nBranches: \n");
+        for branch in &branches {
+            branch.print(Some(&stylize("  - ", "green")));
         }
 
-        println!("\n{}", stylize("Done", "green"));
+        let diverged: Vec<&str> = branches
+            .iter()
+            .filter(|b| matches!(b.relation, Relation::Diverged(..)))
+            .map(|b| b.name.as_str())
+            .collect();
+
+        if !diverged.is_empty() {
+            println!(
+                "\n{} {}",
+                stylize("Diverged branches will be skipped:", "yellow"),
+                diverged.join(", ")
+            );
+        }
+
+        println!();
+    }
+
+    let mut plan = Plan::from_branches(&branches, remote_to.map(String::from));
+    let mut report = Report {
+        branches,
+        ..Default::default()
+    };
+    add_refs(&mut plan, &mut report, args, git)?;
+
+    let state = State::new(plan.clone(), git)?;
+    execute(&plan, report, Some(state), args, git)
+}
 
+/// Fetch a remote before building the plan (or its preview), showing git's output unless
+/// `--json` was given
+fn fetch(args: &Args, git: &Git, remote: &str) -> Result<()> {
+    if args.json {
+        git.exec_and_get_result("fetch", &[remote])?;
         Ok(())
     } else {
+        git.exec("fetch", &vec![s!(remote)])
+    }
+}
+
+/// Add the tags (`--tags`) and notes (`--notes`) synchronisation to the plan.
+///
+/// Conflicting tags (same name, different object) are shown here, before anything gets executed,
+/// and are left out of the plan.
+fn add_refs(plan: &mut Plan, report: &mut Report, args: &Args, git: &Git) -> Result<()> {
+    let (remote_from, remote_to) = (args.remote_from(), args.remote_to());
+
+    if args.tags {
+        let tags = TagSync::collect(git, remote_from, remote_to)?;
+
+        if !tags.conflicts.is_empty() && !args.json {
+            println!(
+                "{}\n",
+                stylize(
+                    "Conflicting tags (same name, different object) will be skipped:",
+                    "yellow"
+                )
+            );
+            for conflict in &tags.conflicts {
+                conflict.print(Some(&stylize("  - ", "yellow")), remote_from);
+            }
+            println!();
+        }
+
+        plan.add_tags(remote_from, remote_to, &tags);
+        report.tag_conflicts = tags.conflicts;
+    }
+
+    if args.notes {
+        plan.add_notes(remote_from, remote_to);
+    }
+
+    Ok(())
+}
+
+/// Resume (`--continue`) or undo (`--abort`) a plan interrupted by a failed command.
+fn handle_resume(resume: Resume, args: &Args, git: &Git) -> Result<()> {
+    let Some(mut state) = State::load(git)? else {
+        return fail!("No sync in progress");
+    };
+
+    let rebase_in_progress = state::rebase_in_progress(git);
+
+    match resume {
+        Resume::Continue => {
+            if rebase_in_progress {
+                state.plan.continue_rebase(state.completed);
+            }
+
+            let plan = state.plan.remaining(state.completed);
+            execute(&plan, Report::default(), Some(state), args, git)
+        }
+        Resume::Abort => {
+            let plan = state.abort_plan(&Snapshot::take(git)?, rebase_in_progress);
+            let pushes = state.completed_pushes();
+
+            if !pushes.is_empty() && !args.json {
+                println!("{}\n", stylize("These pushes were already executed:", "yellow"));
+                for push in pushes {
+                    println!("{}{}", stylize("  - ", "yellow"), push);
+                }
+                println!();
+            }
+
+            execute(&plan, Report::default(), None, args, git)
+        }
+    }
+}
+
+/// Print the plan, ask for confirmation (unless `--yes`) and then execute the commands of the
+/// plan one by one. With `--dry-run` nothing gets executed, and with `--json` the output of the
+/// commands is captured and printed as part of the JSON report instead.
+///
+/// If a `State` is given, it's persisted before the first command and updated after each one, so
+/// the plan can be resumed with `--continue` or undone with `--abort` if a command fails. Once the
+/// whole plan is executed, the state (if any) is removed.
+fn execute(
+    plan: &Plan,
+    mut report: Report,
+    mut state: Option<State>,
+    args: &Args,
+    git: &Git,
+) -> Result<()> {
+    report.set_plan(plan);
+
+    if plan.is_empty() && !args.dry_run {
+        State::remove(git)?;
+    }
+
+    if args.json && (args.dry_run || plan.is_empty()) {
+        return report.print();
+    }
+
+    if plan.is_empty() {
+        println!("{}", stylize("Nothing to do", "green"));
+        return Ok(());
+    }
+
+    if !args.json {
+        println!("Will execute the following commands: \n");
+        plan.print(Some(&stylize("  - ", "green")));
+    }
+
+    if args.dry_run {
+        println!("\n{}", stylize("Dry run, nothing was executed", "+dim"));
+        return Ok(());
+    }
+
+    if !args.yes && !confirm("Do you want to continue?")? {
         eprintln!("\n{}\n", stylize("Aborted", "red"));
         // exit with a non-zero status code
         std::process::exit(1);
     }
+
+    if let Some(state) = &state {
+        state.save(git)?;
+    }
+
+    if !args.json {
+        println!("\n{}\n", stylize("Executing commands...", "cyan"));
+    }
+
+    let commands = plan.commands();
+    for (i, (cmd, cmd_args)) in commands.iter().enumerate() {
+        let success = if args.json {
+            let result = StepResult::run(git, cmd, cmd_args)?;
+            let success = result.success();
+            report.results.push(result);
+            success
+        } else {
+            git.exec(cmd, cmd_args).is_ok()
+        };
+
+        if !success {
+            let failed = format!("Failed to execute: git {} {}", cmd, cmd_args.join(" "));
+
+            if args.json {
+                report.print()?;
+            } else if state.is_some() {
+                eprintln!(
+                    "\n{}\nResolve the problem and run {} to resume, or {} to undo the sync",
+                    stylize(failed, "red"),
+                    stylize("sync --continue", "yellow"),
+                    stylize("sync --abort", "yellow")
+                );
+            } else {
+                eprintln!("\n{}", stylize(failed, "red"));
+            }
+
+            // exit with a non-zero status code
+            std::process::exit(1);
+        }
+
+        if let Some(state) = &mut state {
+            state.complete_step(git)?;
+        }
+
+        if !args.json && i < commands.len() - 1 {
+            println!();
+        }
+    }
+
+    State::remove(git)?;
+
+    if args.json {
+        return report.print();
+    }
+
+    println!("\n{}", stylize("Done", "green"));
+
+    Ok(())
 }
 
-fn confirm(msg: &str) -> Result<()> {
+/// Ask the user for confirmation (Y/N), returning whether they confirmed or not.
+///
+/// Fails if stdin is not a terminal, since there would be no one to answer the prompt.
+fn confirm(msg: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return fail!(
+            "{}\nUse {} to skip the confirmation or {} to only print the plan",
+            stylize("Can't ask for confirmation: stdin is not a terminal", "red"),
+            stylize("--yes", "yellow"),
+            stylize("--dry-run", "yellow")
+        );
+    }
+
     // Print the prompt message without a newline
     print!(
         "\n{} {} {}",
@@ -167,18 +501,21 @@ fn confirm(msg: &str) -> Result<()> {
         print!("\r{} {} {}\n", stylize("❱", "green"), msg, stylize("Y", "green"));
         io::stdout().flush()?; // Flush again to ensure it appears immediately
 
-        Ok(())
+        Ok(true)
     } else {
         print!("\x1b[1A\x1b[2K"); // ANSI escape to move up and clear the line
         print!("\r{} {} {}\n", stylize("❱", "green"), msg, stylize("N", "red"));
         io::stdout().flush()?; // Flush again to ensure it appears immediately
 
-        fail!("Aborted")
+        Ok(false)
     }
 }
 
 fn check_args(args: &Vec<String>) -> Result<()> {
-    let err = fail!("Usage: sync from <remote[:branch]> [to <remote>]");
+    let err = fail!(
+        "Usage: sync from <remote[:branch]> [to <remote>] [--branches <pattern> | --all-tracking] \
+         [--tags] [--notes] [--yes] [--dry-run] [--json] | --continue | --abort"
+    );
 
     if args.is_empty() || args[0] != "from" || args.len() < 2 {
         return err;
@@ -1,17 +1,25 @@
 use {
+    crate::{
+        branches::{BranchState, Relation},
+        tags::TagSync,
+    },
     eyre::Result,
     lool::{cli::stylize::stylize, fail, s},
+    serde::{Deserialize, Serialize},
 };
 
 /// A plan of actions to be executed a list of (git command, its args)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Plan(Vec<(String, Vec<String>)>);
 
 impl Plan {
-    /// Create a new plan from arguments
+    /// Create a new plan from arguments. It starts by fetching the branch from the source remote,
+    /// unless `fetch` is false because it was fetched already.
     pub fn from_args<T: Into<String>>(
         args: Vec<String>,
         default_branch: T,
         remotes: Option<Vec<(String, String)>>,
+        fetch: bool,
     ) -> Result<Self> {
         let mut commands = Vec::new();
 
@@ -35,27 +43,13 @@ impl Plan {
         }
 
         if let Some(remotes) = remotes {
-            // make sure the remotes_to and remotes_from are valid if we have them
-            if !remotes.iter().any(|(name, _)| name == &remote_from) {
-                return fail!(
-                    "Remote '{remote_from}' not found\nRun {} to add the remote",
-                    stylize(format!("git remote add {remote_from} <url>"), "bright-blue"),
-                );
-            }
-            if let Some(ref remote) = remote_to {
-                if !remotes.iter().any(|(name, _)| name == remote) {
-                    return fail!(
-                        "Remote '{remote}' not found\nRun {} to add the remote",
-                        stylize(format!("git remote add {remote} <url>"), "bright-blue"),
-                    );
-                }
-            }
+            check_remotes(&remotes, &remote_from, remote_to.as_deref())?;
         }
 
-        // Fetch the changes from the remote
-        if branch == "master" {
+        // Fetch the changes from the remote, unless it was fetched already
+        if fetch && branch == "master" {
             commands.push((s!("fetch"), vec![remote_from.clone()]));
-        } else {
+        } else if fetch {
             commands.push((s!("fetch"), vec![remote_from.clone(), branch.clone()]));
         }
 
@@ -75,6 +69,78 @@ impl Plan {
         Ok(Plan(commands))
     }
 
+    /// Create a new plan to sync several local branches at once with their counterparts in the
+    /// source remote (`--branches <pattern>` or `--all-tracking`).
+    ///
+    /// Branches that are behind are fast-forwarded with `git update-ref`, so they don't need to be
+    /// checked out; the current branch is rebased instead, to keep the working tree up to date.
+    /// Diverged branches are left untouched (and aren't pushed). The remote is expected to be
+    /// fetched already, since the branch states are computed from the remote-tracking branches.
+    pub fn from_branches(branches: &[BranchState], remote_to: Option<String>) -> Self {
+        let mut commands = Vec::new();
+        let mut to_push = Vec::new();
+
+        for branch in branches {
+            match branch.relation {
+                Relation::Diverged(..) => continue,
+                Relation::Behind(_) if branch.current => {
+                    commands.push((s!("rebase"), vec![branch.counterpart.clone()]));
+                }
+                Relation::Behind(_) => {
+                    commands.push((
+                        s!("update-ref"),
+                        vec![
+                            format!("refs/heads/{}", branch.name),
+                            branch.counterpart_sha.clone(),
+                            branch.sha.clone(),
+                        ],
+                    ));
+                }
+                Relation::UpToDate | Relation::Ahead(_) => {}
+            }
+
+            to_push.push(branch.name.clone());
+        }
+
+        if let Some(remote) = remote_to {
+            if !to_push.is_empty() {
+                let mut args = vec![remote];
+                args.extend(to_push);
+                commands.push((s!("push"), args));
+            }
+        }
+
+        Plan(commands)
+    }
+
+    /// Add the commands to fetch the missing tags from the source remote and push them to the
+    /// destination remote (`--tags`). Conflicting tags are never part of the plan.
+    pub fn add_tags(&mut self, remote_from: &str, remote_to: Option<&str>, tags: &TagSync) {
+        if !tags.fetch.is_empty() {
+            let mut args = vec![s!(remote_from), s!("--no-tags")];
+            args.extend(tags.fetch.iter().map(|tag| format!("refs/tags/{tag}:refs/tags/{tag}")));
+            self.0.push((s!("fetch"), args));
+        }
+
+        if let Some(remote) = remote_to {
+            if !tags.push.is_empty() {
+                let mut args = vec![s!(remote)];
+                args.extend(tags.push.iter().map(|tag| format!("refs/tags/{tag}")));
+                self.0.push((s!("push"), args));
+            }
+        }
+    }
+
+    /// Add the commands to fetch the notes (`refs/notes/*`) from the source remote and push them
+    /// to the destination remote (`--notes`).
+    pub fn add_notes(&mut self, remote_from: &str, remote_to: Option<&str>) {
+        self.0.push((s!("fetch"), vec![s!(remote_from), s!("refs/notes/*:refs/notes/*")]));
+
+        if let Some(remote) = remote_to {
+            self.0.push((s!("push"), vec![s!(remote), s!("refs/notes/*:refs/notes/*")]));
+        }
+    }
+
     pub fn print(&self, prefix: Option<&str>) {
         for (cmd, args) in &self.0 {
             println!(
@@ -90,13 +156,57 @@ impl Plan {
     pub fn commands(&self) -> &Vec<(String, Vec<String>)> {
         &self.0
     }
+
+    /// Add a command at the end of the plan
+    pub fn push<T: Into<String>>(&mut self, cmd: T, args: Vec<String>) {
+        self.0.push((cmd.into(), args));
+    }
+
+    /// A new plan with the commands starting at the given step
+    pub fn remaining(&self, step: usize) -> Plan {
+        Plan(self.0[step.min(self.0.len())..].to_vec())
+    }
+
+    /// Replace the `rebase` at the given step with `rebase --continue`, so a plan interrupted by
+    /// a conflict can be resumed once the user resolved it. The editor is disabled since the
+    /// output of the commands is not a terminal.
+    pub fn continue_rebase(&mut self, step: usize) {
+        if let Some((cmd, _)) = self.0.get(step) {
+            if cmd == "rebase" {
+                self.0[step] =
+                    (s!("-c"), vec![s!("core.editor=true"), s!("rebase"), s!("--continue")]);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Make sure the source and destination remotes exist in the given list of remotes
+pub fn check_remotes(
+    remotes: &[(String, String)],
+    remote_from: &str,
+    remote_to: Option<&str>,
+) -> Result<()> {
+    for remote in std::iter::once(remote_from).chain(remote_to) {
+        if !remotes.iter().any(|(name, _)| name == remote) {
+            return fail!(
+                "Remote '{remote}' not found\nRun {} to add the remote",
+                stylize(format!("git remote add {remote} <url>"), "bright-blue"),
+            );
+        }
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use lool::s;
 
-    use super::Plan;
+    use super::{BranchState, Plan, Relation, TagSync};
 
     fn assert_plan(commands: &Plan, expected: Vec<(&str, Vec<&str>)>) {
         let plan_commands: Vec<(String, Vec<String>)> = commands.0.clone();
@@ -106,13 +216,23 @@ mod tests {
             .collect();
         assert_eq!(plan_commands, expected_commands);
     }
+
+    fn branch(name: &str, relation: Relation, current: bool) -> BranchState {
+        BranchState {
+            name: name.to_string(),
+            sha: format!("{name}-local"),
+            counterpart: format!("origin/{name}"),
+            counterpart_sha: format!("{name}-remote"),
+            relation,
+            current,
+        }
+    }
     #[test]
     fn test_sync_from_gitea() {
         let args = vec![s!("sync"), s!("gitea")];
-        let plan = Plan::from_args(args, "master", None).unwrap();
+        let plan = Plan::from_args(args, "master", None, true).unwrap();
 
-        let expected =
-            vec![("git", vec!["fetch", "gitea"]), ("git", vec!["rebase", "gitea/master"])];
+        let expected = vec![("fetch", vec!["gitea"]), ("rebase", vec!["gitea/master"])];
 
         assert_plan(&plan, expected);
     }
@@ -121,12 +241,12 @@ mod tests {
     fn test_sync_from_gitea_to_github() {
         let args =
             vec!["sync".to_string(), "gitea".to_string(), "to".to_string(), "github".to_string()];
-        let plan = Plan::from_args(args, "master", None).unwrap();
+        let plan = Plan::from_args(args, "master", None, true).unwrap();
 
         let expected = vec![
-            ("git", vec!["fetch", "gitea"]),
-            ("git", vec!["rebase", "gitea/master"]),
-            ("git", vec!["push", "github", "master"]),
+            ("fetch", vec!["gitea"]),
+            ("rebase", vec!["gitea/master"]),
+            ("push", vec!["github", "master"]),
         ];
 
         assert_plan(&plan, expected);
@@ -135,12 +255,12 @@ mod tests {
     #[test]
     fn test_sync_from_github_feature_branch() {
         let args = vec!["sync".to_string(), "github:feature-branch".to_string()];
-        let plan = Plan::from_args(args, "master", None).unwrap();
+        let plan = Plan::from_args(args, "master", None, true).unwrap();
 
         let expected = vec![
-            ("git", vec!["fetch", "github", "feature-branch"]),
-            ("git", vec!["checkout", "feature-branch"]),
-            ("git", vec!["rebase", "github/feature-branch"]),
+            ("fetch", vec!["github", "feature-branch"]),
+            ("checkout", vec!["feature-branch"]),
+            ("rebase", vec!["github/feature-branch"]),
         ];
 
         assert_plan(&plan, expected);
@@ -154,13 +274,92 @@ mod tests {
             "to".to_string(),
             "gitea".to_string(),
         ];
-        let plan = Plan::from_args(args, "master", None).unwrap();
+        let plan = Plan::from_args(args, "master", None, true).unwrap();
+
+        let expected = vec![
+            ("fetch", vec!["github", "feature-branch"]),
+            ("checkout", vec!["feature-branch"]),
+            ("rebase", vec!["github/feature-branch"]),
+            ("push", vec!["gitea", "feature-branch"]),
+        ];
+
+        assert_plan(&plan, expected);
+    }
+
+    #[test]
+    fn test_sync_already_fetched() {
+        let args = vec![s!("sync"), s!("github:feature-branch"), s!("to"), s!("gitea")];
+        let plan = Plan::from_args(args, "master", None, false).unwrap();
+
+        let expected = vec![
+            ("checkout", vec!["feature-branch"]),
+            ("rebase", vec!["github/feature-branch"]),
+            ("push", vec!["gitea", "feature-branch"]),
+        ];
+
+        assert_plan(&plan, expected);
+    }
+
+    #[test]
+    fn test_sync_branches_fast_forward() {
+        let branches = vec![
+            branch("release/1.0", Relation::Behind(2), false),
+            branch("release/2.0", Relation::UpToDate, false),
+        ];
+        let plan = Plan::from_branches(&branches, None);
+
+        let expected = vec![(
+            "update-ref",
+            vec!["refs/heads/release/1.0", "release/1.0-remote", "release/1.0-local"],
+        )];
+
+        assert_plan(&plan, expected);
+    }
+
+    #[test]
+    fn test_sync_branches_rebases_current_branch() {
+        let branches = vec![branch("release/1.0", Relation::Behind(1), true)];
+        let plan = Plan::from_branches(&branches, None);
+
+        let expected = vec![("rebase", vec!["origin/release/1.0"])];
+
+        assert_plan(&plan, expected);
+    }
+
+    #[test]
+    fn test_sync_branches_skips_diverged_to_github() {
+        let branches = vec![
+            branch("release/1.0", Relation::Diverged(1, 3), false),
+            branch("release/2.0", Relation::Ahead(4), false),
+        ];
+        let plan = Plan::from_branches(&branches, Some(s!("github")));
+
+        let expected = vec![("push", vec!["github", "release/2.0"])];
+
+        assert_plan(&plan, expected);
+    }
+
+    #[test]
+    fn test_sync_tags_and_notes_to_github() {
+        let args = vec![s!("sync"), s!("gitea"), s!("to"), s!("github")];
+        let mut plan = Plan::from_args(args, "master", None, true).unwrap();
+        let tags = TagSync {
+            fetch: vec![s!("v1.1")],
+            push: vec![s!("v1.0"), s!("v1.1")],
+            conflicts: vec![],
+        };
+
+        plan.add_tags("gitea", Some("github"), &tags);
+        plan.add_notes("gitea", Some("github"));
 
         let expected = vec![
-            ("git", vec!["fetch", "github", "feature-branch"]),
-            ("git", vec!["checkout", "feature-branch"]),
-            ("git", vec!["rebase", "github/feature-branch"]),
-            ("git", vec!["push", "gitea", "feature-branch"]),
+            ("fetch", vec!["gitea"]),
+            ("rebase", vec!["gitea/master"]),
+            ("push", vec!["github", "master"]),
+            ("fetch", vec!["gitea", "--no-tags", "refs/tags/v1.1:refs/tags/v1.1"]),
+            ("push", vec!["github", "refs/tags/v1.0", "refs/tags/v1.1"]),
+            ("fetch", vec!["gitea", "refs/notes/*:refs/notes/*"]),
+            ("push", vec!["github", "refs/notes/*:refs/notes/*"]),
         ];
 
         assert_plan(&plan, expected);
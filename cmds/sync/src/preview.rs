@@ -0,0 +1,269 @@
+//! # sync/preview
+//!
+//! Preview of what a single-branch sync is about to do, shown along with the plan before asking
+//! for confirmation: the commits coming from the source remote, the local commits that will be
+//! rebased onto them (and the ones that will be dropped because their changes are already
+//! upstream with a different commit id), and the commits that will be pushed to the destination.
+//! Pushing rebased commits to a protected branch (see [`common::protected`]) is warned about,
+//! since it rewrites the history others build on.
+//!
+//! The preview is computed from the remote-tracking branches, so the remotes must be fetched
+//! before collecting it.
+
+use {
+    common::{git::Git, protected::Protected},
+    eyre::Result,
+    lool::{cli::stylize::stylize, fail, s},
+    serde::Serialize,
+};
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Commit {
+    pub sha: String,
+    pub author: String,
+    pub subject: String,
+}
+
+impl Commit {
+    fn print(&self, prefix: &str, color: &str) {
+        println!(
+            "{}{} {} {}",
+            prefix,
+            stylize(&self.sha, color),
+            self.subject,
+            stylize(format!("({})", self.author), "+dim")
+        );
+    }
+}
+
+/// What will be pushed to the destination remote
+#[derive(Serialize, Debug)]
+pub struct Push {
+    /// e.g. `github/master`
+    pub destination: String,
+    pub commits: Vec<Commit>,
+    /// commits in the destination branch that aren't in the source one; if any, the push will be
+    /// rejected since it's not a fast-forward
+    pub rejected: usize,
+    /// whether the destination branch is protected
+    pub protected: bool,
+    /// whether local commits are rebased (getting new ids) before being pushed
+    pub rewritten: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Preview {
+    pub branch: String,
+    /// e.g. `gitea/master`
+    pub source: String,
+    pub ahead: usize,
+    pub behind: usize,
+    /// commits of the source branch that aren't in the local branch
+    pub incoming: Vec<Commit>,
+    /// local commits that will be rebased onto the source branch
+    pub rebased: Vec<Commit>,
+    /// local commits whose changes are already in the source branch (same patch-id, different
+    /// commit); the rebase drops them
+    pub upstream: Vec<Commit>,
+    pub push: Option<Push>,
+}
+
+impl Preview {
+    pub fn collect(
+        git: &Git,
+        branch: &str,
+        remote_from: &str,
+        remote_to: Option<&str>,
+    ) -> Result<Self> {
+        let source = format!("{remote_from}/{branch}");
+        let local = format!("refs/heads/{branch}");
+        let local_exists = rev_exists(git, &local);
+
+        if !rev_exists(git, &source) {
+            return fail!("Branch '{source}' not found");
+        }
+
+        let mut preview = Preview {
+            branch: s!(branch),
+            source: source.clone(),
+            ahead: 0,
+            behind: 0,
+            incoming: Vec::new(),
+            rebased: Vec::new(),
+            upstream: Vec::new(),
+            push: None,
+        };
+
+        if local_exists {
+            let range = format!("{local}...{source}");
+
+            preview.incoming = log(git, &["--cherry-pick", "--right-only", &range])?
+                .into_iter()
+                .map(|(_, commit)| commit)
+                .collect();
+
+            for (mark, commit) in log(git, &["--cherry-mark", "--left-only", &range])? {
+                if mark == "=" {
+                    preview.upstream.push(commit);
+                } else {
+                    preview.rebased.push(commit);
+                }
+            }
+
+            let counts =
+                git.exec_and_get_result("rev-list", &["--left-right", "--count", &range])?;
+            let counts: Vec<usize> =
+                counts.split_whitespace().filter_map(|count| count.parse().ok()).collect();
+
+            if let [ahead, behind] = counts.as_slice() {
+                (preview.ahead, preview.behind) = (*ahead, *behind);
+            }
+        } else {
+            // the branch will be created from the source one
+            preview.incoming = log(git, &[&source, "--not", "--branches"])?
+                .into_iter()
+                .map(|(_, commit)| commit)
+                .collect();
+            preview.behind = preview.incoming.len();
+        }
+
+        if let Some(remote) = remote_to {
+            let destination = format!("{remote}/{branch}");
+
+            let (new, rejected) = if rev_exists(git, &destination) {
+                let rejected = git.exec_and_get_result(
+                    "rev-list",
+                    &["--count", &format!("{source}..{destination}")],
+                )?;
+                (log(git, &[&format!("{destination}..{source}")])?, rejected.trim().parse()?)
+            } else {
+                (log(git, &[&source, "--not", &format!("--remotes={remote}")])?, 0)
+            };
+
+            // the rebased commits are new commits, so they will be pushed too
+            let mut commits = preview.rebased.clone();
+            commits.extend(new.into_iter().map(|(_, commit)| commit));
+
+            preview.push = Some(Push {
+                destination,
+                commits,
+                rejected,
+                protected: Protected::load()?.matches(branch),
+                rewritten: !preview.rebased.is_empty() && !preview.incoming.is_empty(),
+            });
+        }
+
+        Ok(preview)
+    }
+
+    pub fn print(&self) {
+        let bullet = stylize("  │ ", "+dim");
+
+        println!(
+            "{} {} {} {}",
+            stylize(&self.branch, "bright-blue+bold"),
+            stylize("→", "+dim"),
+            stylize(&self.source, "magenta"),
+            stylize(format!("(ahead {}, behind {})", self.ahead, self.behind), "+dim")
+        );
+
+        if !self.incoming.is_empty() {
+            println!("\n{}", stylize(format!("Incoming from {}:", self.source), "cyan"));
+            for commit in &self.incoming {
+                commit.print(&bullet, "green");
+            }
+        }
+
+        if !self.rebased.is_empty() {
+            println!("\n{}", stylize(format!("Will be rebased onto {}:", self.source), "cyan"));
+            for commit in &self.rebased {
+                commit.print(&bullet, "yellow");
+            }
+        }
+
+        if !self.upstream.is_empty() {
+            println!(
+                "\n{}",
+                stylize("Already upstream with a different commit (will be dropped):", "cyan")
+            );
+            for commit in &self.upstream {
+                commit.print(&bullet, "+dim");
+            }
+        }
+
+        if let Some(push) = &self.push {
+            if push.commits.is_empty() {
+                println!(
+                    "\n{}",
+                    stylize(format!("Nothing new to push to {}", push.destination), "cyan")
+                );
+            } else {
+                println!(
+                    "\n{}",
+                    stylize(format!("Will be pushed to {}:", push.destination), "cyan")
+                );
+                for commit in &push.commits {
+                    commit.print(&bullet, "magenta");
+                }
+            }
+
+            if push.protected && push.rewritten {
+                println!(
+                    "\n{}",
+                    stylize(
+                        format!(
+                            "{} is a protected branch and will get rebased history pushed to it",
+                            push.destination
+                        ),
+                        "yellow"
+                    )
+                );
+            }
+
+            if push.rejected > 0 {
+                println!(
+                    "\n{}",
+                    stylize(
+                        format!(
+                            "{} has {} commit(s) not in {}; the push will be rejected",
+                            push.destination, push.rejected, self.source
+                        ),
+                        "red"
+                    )
+                );
+            }
+        }
+
+        println!();
+    }
+}
+
+fn rev_exists(git: &Git, rev: &str) -> bool {
+    git.exec_and_get_result("rev-parse", &["--verify", "-q", rev]).is_ok()
+}
+
+/// Run `git log` with the given arguments and return the (mark, commit) of each listed commit
+fn log(git: &Git, args: &[&str]) -> Result<Vec<(String, Commit)>> {
+    let mut log_args = vec!["--format=%m%x00%h%x00%an%x00%s"];
+    log_args.extend(args);
+
+    let output = git.exec_and_get_result("log", &log_args)?;
+
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.splitn(4, '\0').collect();
+            match fields.as_slice() {
+                [mark, sha, author, subject] => Some((
+                    s!(*mark),
+                    Commit {
+                        sha: s!(*sha),
+                        author: s!(*author),
+                        subject: s!(*subject),
+                    },
+                )),
+                _ => None,
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,81 @@
+//! # sync/report
+//!
+//! Machine-readable output of `sync --json`: the plan, its preview, anything that was left out of
+//! it (diverged branches, conflicting tags) and the result of each executed step.
+
+use {
+    crate::{branches::BranchState, plan::Plan, preview::Preview, tags::TagConflict},
+    common::git::Git,
+    eyre::Result,
+    serde::Serialize,
+    std::time::Instant,
+};
+
+/// A command of the plan
+#[derive(Serialize, Debug)]
+pub struct Step {
+    pub cmd: String,
+    pub args: Vec<String>,
+}
+
+/// The outcome of an executed command of the plan
+#[derive(Serialize, Debug)]
+pub struct StepResult {
+    pub command: String,
+    /// `null` if git was terminated by a signal
+    pub exit_code: Option<i32>,
+    pub duration_ms: u128,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl StepResult {
+    /// Execute a git command capturing its output
+    pub fn run(git: &Git, cmd: &str, args: &[String]) -> Result<Self> {
+        let start = Instant::now();
+        let output = git.exec_and_capture(cmd, args)?;
+
+        Ok(StepResult {
+            command: format!("git {} {}", cmd, args.join(" ")),
+            exit_code: output.status.code(),
+            duration_ms: start.elapsed().as_millis(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct Report {
+    pub plan: Vec<Step>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<Preview>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub branches: Vec<BranchState>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tag_conflicts: Vec<TagConflict>,
+    pub results: Vec<StepResult>,
+}
+
+impl Report {
+    pub fn set_plan(&mut self, plan: &Plan) {
+        self.plan = plan
+            .commands()
+            .iter()
+            .map(|(cmd, args)| Step {
+                cmd: cmd.clone(),
+                args: args.clone(),
+            })
+            .collect();
+    }
+
+    /// Print the report as JSON to stdout
+    pub fn print(&self) -> Result<()> {
+        println!("{}", serde_json::to_string_pretty(self)?);
+        Ok(())
+    }
+}
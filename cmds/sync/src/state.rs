@@ -0,0 +1,276 @@
+//! # sync/state
+//!
+//! On-disk state of an executing plan, stored in `.git/gitools/sync-state`.
+//!
+//! The state is written before the first command is executed and updated after each completed
+//! command, along with a snapshot of the refs at that point. If a command fails (e.g. a rebase
+//! conflict), the state is kept so `sync --continue` can resume the plan after the failed step
+//! and `sync --abort` can restore the branches to where they were before the plan started. Once
+//! the plan is completed, the state is removed.
+
+use {
+    crate::plan::Plan,
+    common::git::Git,
+    eyre::{Context, Result},
+    lool::{fail, s},
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, fs, path::PathBuf},
+};
+
+/// The refs of the repository at a given point of the plan
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    /// checked out branch, `None` if HEAD is detached
+    pub branch: Option<String>,
+    pub head: String,
+    /// local branch name → commit
+    pub refs: BTreeMap<String, String>,
+}
+
+impl Snapshot {
+    pub fn take(git: &Git) -> Result<Self> {
+        let branch = git
+            .exec_and_get_result("symbolic-ref", &["-q", "--short", "HEAD"])
+            .ok()
+            .map(|branch| s!(branch.trim()));
+        let head = s!(git.exec_and_get_result("rev-parse", &["HEAD"])?.trim());
+        let refs = git
+            .exec_and_get_result(
+                "for-each-ref",
+                &["--format=%(refname:short) %(objectname)", "refs/heads"],
+            )?
+            .lines()
+            .filter_map(|line| {
+                let (name, oid) = line.split_once(' ')?;
+                Some((s!(name), s!(oid)))
+            })
+            .collect();
+
+        Ok(Snapshot { branch, head, refs })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct State {
+    pub plan: Plan,
+    /// number of completed steps; it's also the index of the step to run next
+    pub completed: usize,
+    /// the refs before the first step
+    pub initial: Snapshot,
+    /// the refs after each completed step
+    pub steps: Vec<Snapshot>,
+}
+
+impl State {
+    pub fn new(plan: Plan, git: &Git) -> Result<Self> {
+        Ok(State {
+            plan,
+            completed: 0,
+            initial: Snapshot::take(git)?,
+            steps: Vec::new(),
+        })
+    }
+
+    /// Load the state of the interrupted plan, if any
+    pub fn load(git: &Git) -> Result<Option<Self>> {
+        let path = path(git);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path)?;
+        let state = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse the sync state in {}", path.display()))?;
+
+        Ok(Some(state))
+    }
+
+    pub fn save(&self, git: &Git) -> Result<()> {
+        let path = path(git);
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn remove(git: &Git) -> Result<()> {
+        let path = path(git);
+
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Mark the next step as completed, recording the refs after it
+    pub fn complete_step(&mut self, git: &Git) -> Result<()> {
+        self.completed += 1;
+        self.steps.push(Snapshot::take(git)?);
+        self.save(git)
+    }
+
+    /// The pushes which were already executed; they can't be undone by `--abort`
+    pub fn completed_pushes(&self) -> Vec<String> {
+        self.plan.commands()[..self.completed.min(self.plan.commands().len())]
+            .iter()
+            .filter(|(cmd, _)| cmd == "push")
+            .map(|(cmd, args)| format!("git {} {}", cmd, args.join(" ")))
+            .collect()
+    }
+
+    /// The local branches the plan may move: the initially checked out one, the ones checked out
+    /// by the plan and the ones fast-forwarded with `update-ref`.
+    fn touched_branches(&self) -> Vec<String> {
+        let mut branches: Vec<String> = self.initial.branch.iter().cloned().collect();
+
+        for (cmd, args) in self.plan.commands() {
+            let branch = match (cmd.as_str(), args.first()) {
+                ("checkout", Some(branch)) => s!(branch),
+                ("update-ref", Some(name)) => s!(name.trim_start_matches("refs/heads/")),
+                _ => continue,
+            };
+
+            if !branches.contains(&branch) {
+                branches.push(branch);
+            }
+        }
+
+        branches
+    }
+
+    /// Build the plan that undoes the interrupted one, given the current refs:
+    ///
+    /// - abort the rebase in progress, if any
+    /// - go back to the initially checked out branch (or commit)
+    /// - move the branches touched by the plan back to where they were, and delete the ones the
+    ///   plan created
+    ///
+    /// Fetched refs and pushes are not undone.
+    pub fn abort_plan(&self, current: &Snapshot, rebase_in_progress: bool) -> Plan {
+        let mut plan = Plan::default();
+
+        if rebase_in_progress {
+            plan.push("rebase", vec![s!("--abort")]);
+        }
+
+        match &self.initial.branch {
+            Some(branch) if rebase_in_progress || current.branch.as_ref() != Some(branch) => {
+                plan.push("checkout", vec![branch.clone()]);
+            }
+            None if current.head != self.initial.head => {
+                plan.push("checkout", vec![s!("--detach"), self.initial.head.clone()]);
+            }
+            _ => {}
+        }
+
+        for branch in self.touched_branches() {
+            let initial = self.initial.refs.get(&branch);
+            let now = current.refs.get(&branch);
+
+            match (initial, now) {
+                (Some(initial), Some(now)) if initial != now => {
+                    if self.initial.branch.as_ref() == Some(&branch) {
+                        plan.push("reset", vec![s!("--keep"), initial.clone()]);
+                    } else {
+                        plan.push(
+                            "update-ref",
+                            vec![format!("refs/heads/{branch}"), initial.clone(), now.clone()],
+                        );
+                    }
+                }
+                (None, Some(_)) => plan.push("branch", vec![s!("-D"), branch]),
+                _ => {}
+            }
+        }
+
+        plan
+    }
+}
+
+/// Fail if there is an interrupted plan, so it's not overwritten by a new one
+pub fn ensure_no_state(git: &Git) -> Result<()> {
+    if path(git).exists() {
+        return fail!(
+            "A sync is already in progress\nUse `sync --continue` to resume it or `sync --abort` \
+             to undo it"
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether a rebase is in progress in the repository
+pub fn rebase_in_progress(git: &Git) -> bool {
+    let git_dir = git.get_repo_path().join(".git");
+    git_dir.join("rebase-merge").exists() || git_dir.join("rebase-apply").exists()
+}
+
+fn path(git: &Git) -> PathBuf {
+    git.get_repo_path().join(".git").join("gitools").join("sync-state")
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{Snapshot, State},
+        crate::plan::Plan,
+        lool::s,
+        std::collections::BTreeMap,
+    };
+
+    fn snapshot(branch: Option<&str>, refs: &[(&str, &str)]) -> Snapshot {
+        let refs: BTreeMap<String, String> =
+            refs.iter().map(|(name, oid)| (name.to_string(), oid.to_string())).collect();
+
+        Snapshot {
+            branch: branch.map(String::from),
+            head: branch.and_then(|b| refs.get(b)).cloned().unwrap_or_default(),
+            refs,
+        }
+    }
+
+    fn commands(plan: &Plan) -> Vec<String> {
+        plan.commands().iter().map(|(cmd, args)| format!("{} {}", cmd, args.join(" "))).collect()
+    }
+
+    #[test]
+    fn test_abort_during_rebase_of_new_branch() {
+        let args = vec![s!("sync"), s!("github:feature"), s!("to"), s!("gitea")];
+        let state = State {
+            plan: Plan::from_args(args, "master", None, true).unwrap(),
+            completed: 2,
+            initial: snapshot(Some("master"), &[("master", "aaa")]),
+            steps: vec![],
+        };
+        let current = snapshot(None, &[("master", "aaa"), ("feature", "bbb")]);
+
+        let plan = state.abort_plan(&current, true);
+
+        assert_eq!(commands(&plan), vec!["rebase --abort", "checkout master", "branch -D feature"]);
+    }
+
+    #[test]
+    fn test_abort_restores_fast_forwarded_branches() {
+        let mut plan = Plan::default();
+        plan.push("update-ref", vec![s!("refs/heads/release/1"), s!("ccc"), s!("bbb")]);
+        plan.push("push", vec![s!("github"), s!("release/1")]);
+
+        let state = State {
+            plan,
+            completed: 2,
+            initial: snapshot(Some("master"), &[("master", "aaa"), ("release/1", "bbb")]),
+            steps: vec![],
+        };
+        let current = snapshot(Some("master"), &[("master", "aaa"), ("release/1", "ccc")]);
+
+        let plan = state.abort_plan(&current, false);
+
+        assert_eq!(commands(&plan), vec!["update-ref refs/heads/release/1 bbb ccc"]);
+        assert_eq!(state.completed_pushes(), vec!["git push github release/1"]);
+    }
+}
@@ -0,0 +1,193 @@
+//! # sync/tags
+//!
+//! Tags synchronisation (`--tags`). The tags of the source remote are compared with the local ones
+//! and with the ones in the destination remote, so the plan only fetches/pushes the tags that are
+//! missing. Tags with the same name pointing at a different object are reported as conflicts and
+//! are never fetched or pushed.
+
+use {
+    common::git::Git,
+    eyre::Result,
+    lool::{cli::stylize::stylize, s},
+    serde::Serialize,
+    std::collections::BTreeMap,
+};
+
+/// tag name → object id
+pub type Tags = BTreeMap<String, String>;
+
+/// A tag of the source remote which points at a different object somewhere else
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TagConflict {
+    pub name: String,
+    pub source: String,
+    /// where the conflicting tag was found: `local` or the name of the destination remote
+    pub place: String,
+    pub other: String,
+}
+
+impl TagConflict {
+    pub fn print(&self, prefix: Option<&str>, remote_from: &str) {
+        println!(
+            "{}{} {} {} {} {} {}",
+            prefix.unwrap_or(""),
+            stylize(&self.name, "yellow"),
+            stylize(remote_from, "+dim"),
+            stylize(short(&self.source), "magenta"),
+            stylize("≠", "red"),
+            stylize(&self.place, "+dim"),
+            stylize(short(&self.other), "magenta"),
+        );
+    }
+}
+
+/// The tags to fetch from the source remote and push to the destination remote
+#[derive(Debug, Default, PartialEq)]
+pub struct TagSync {
+    pub fetch: Vec<String>,
+    pub push: Vec<String>,
+    pub conflicts: Vec<TagConflict>,
+}
+
+impl TagSync {
+    /// List the tags of the source remote, the local repo and the destination remote (if any)
+    /// and compare them.
+    pub fn collect(git: &Git, remote_from: &str, remote_to: Option<&str>) -> Result<Self> {
+        let source = remote_tags(git, remote_from)?;
+        let local = local_tags(git)?;
+        let dest = match remote_to {
+            Some(remote) => Some((remote, remote_tags(git, remote)?)),
+            None => None,
+        };
+
+        Ok(Self::compare(&source, &local, dest.as_ref().map(|(name, tags)| (*name, tags))))
+    }
+
+    /// Compare the tags of the source remote with the local ones and with the ones of the
+    /// destination remote.
+    pub fn compare(source: &Tags, local: &Tags, dest: Option<(&str, &Tags)>) -> Self {
+        let mut sync = TagSync::default();
+
+        for (name, oid) in source {
+            let conflict = |place: &str, other: &String| TagConflict {
+                name: name.clone(),
+                source: oid.clone(),
+                place: s!(place),
+                other: other.clone(),
+            };
+
+            let mut conflicts = Vec::new();
+
+            if let Some(other) = local.get(name).filter(|other| *other != oid) {
+                conflicts.push(conflict("local", other));
+            }
+
+            if let Some((remote, tags)) = dest {
+                if let Some(other) = tags.get(name).filter(|other| *other != oid) {
+                    conflicts.push(conflict(remote, other));
+                }
+            }
+
+            if !conflicts.is_empty() {
+                sync.conflicts.extend(conflicts);
+                continue;
+            }
+
+            if !local.contains_key(name) {
+                sync.fetch.push(name.clone());
+            }
+
+            if let Some((_, tags)) = dest {
+                if !tags.contains_key(name) {
+                    sync.push.push(name.clone());
+                }
+            }
+        }
+
+        sync
+    }
+}
+
+/// List the tags of a remote using `git ls-remote --tags --refs <remote>`
+fn remote_tags(git: &Git, remote: &str) -> Result<Tags> {
+    let output = git.exec_and_get_result("ls-remote", &["--tags", "--refs", remote])?;
+
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let (oid, name) = line.split_once('\t')?;
+            Some((s!(name.trim().trim_start_matches("refs/tags/")), s!(oid.trim())))
+        })
+        .collect())
+}
+
+/// List the local tags using `git for-each-ref refs/tags`
+fn local_tags(git: &Git) -> Result<Tags> {
+    let output = git.exec_and_get_result(
+        "for-each-ref",
+        &["--format=%(objectname) %(refname:strip=2)", "refs/tags"],
+    )?;
+
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let (oid, name) = line.split_once(' ')?;
+            Some((s!(name.trim()), s!(oid.trim())))
+        })
+        .collect())
+}
+
+fn short(oid: &str) -> &str {
+    &oid[..oid.len().min(7)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TagConflict, TagSync, Tags};
+
+    fn tags(list: &[(&str, &str)]) -> Tags {
+        list.iter().map(|(name, oid)| (name.to_string(), oid.to_string())).collect()
+    }
+
+    #[test]
+    fn test_tags_missing_locally_and_in_destination() {
+        let source = tags(&[("v1.0", "aaa"), ("v1.1", "bbb")]);
+        let local = tags(&[("v1.0", "aaa")]);
+        let dest = tags(&[]);
+
+        let sync = TagSync::compare(&source, &local, Some(("github", &dest)));
+
+        assert_eq!(sync.fetch, vec!["v1.1"]);
+        assert_eq!(sync.push, vec!["v1.0", "v1.1"]);
+        assert!(sync.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_conflicting_tags_are_skipped() {
+        let source = tags(&[("v1.0", "aaa"), ("v1.1", "bbb")]);
+        let local = tags(&[("v1.0", "ccc")]);
+        let dest = tags(&[("v1.1", "ddd")]);
+
+        let sync = TagSync::compare(&source, &local, Some(("github", &dest)));
+
+        assert!(sync.fetch.is_empty());
+        assert!(sync.push.is_empty());
+        assert_eq!(
+            sync.conflicts,
+            vec![
+                TagConflict {
+                    name: "v1.0".to_string(),
+                    source: "aaa".to_string(),
+                    place: "local".to_string(),
+                    other: "ccc".to_string(),
+                },
+                TagConflict {
+                    name: "v1.1".to_string(),
+                    source: "bbb".to_string(),
+                    place: "github".to_string(),
+                    other: "ddd".to_string(),
+                },
+            ]
+        );
+    }
+}
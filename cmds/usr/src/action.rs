@@ -1,14 +1,27 @@
 use {
-    crate::profiles,
-    common::{cli::print, git},
+    crate::{
+        args::ProfileArgs,
+        bundle::{self, Resolution},
+        profiles, rules,
+        signing::{self, Format},
+    },
+    common::{
+        cli::{context::ExecutionContext, print},
+        git::{self, Git},
+    },
     eyre::Result,
     inquire::{
         required,
         ui::{Attributes, Color, RenderConfig, StyleSheet, Styled},
-        Select, Text,
+        validator::{StringValidator, Validation},
+        Confirm, Select, Text,
     },
-    lool::{cli::stylize::stylize, fail},
+    lool::{cli::stylize::stylize, fail, s},
     rand::{distributions::Alphanumeric, thread_rng, Rng},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
     tabled::{
         settings::{
             object::{Columns, Rows},
@@ -50,7 +63,7 @@ fn get_active_profile_id() -> Option<String> {
 
 /// Show the config file content.
 pub fn show_config() -> Result<()> {
-    let config = profiles::Config::load();
+    let config = profiles::Config::load()?;
     let content = toml::to_string_pretty(&config);
     if content.is_err() {
         return fail!("Failed to serialize the config file");
@@ -60,24 +73,99 @@ pub fn show_config() -> Result<()> {
     Ok(())
 }
 
-/// Show the current active user profile or Fail if none is set.
-pub fn view() -> Result<()> {
-    let config = profiles::Config::load();
+/// The remotes and root directory of the repository in the current directory, if any
+fn current_repo() -> Option<(Vec<(String, String)>, PathBuf)> {
+    let git = Git::new(&ExecutionContext::new().ok()?).ok()?;
+    Some((git.config().get_remotes(), git.get_repo_path().clone()))
+}
+
+/// The first profile with a rule matching the repository in the current directory, along with
+/// the rule and what it matched
+fn matching_profile(
+    profiles: &[profiles::Profile],
+    remotes: &[(String, String)],
+    path: &Path,
+) -> Option<(usize, String)> {
+    let repo = rules::Repo { remotes, path };
+
+    profiles.iter().enumerate().find_map(|(i, p)| {
+        rules::find_match(&p.rules, &repo)
+            .map(|(rule, matched)| (i, format!("{rule} for {matched}")))
+    })
+}
+
+/// Warn if the active profile isn't the one the rules pick for the repository in the current
+/// directory
+fn check_rules(profiles: &[profiles::Profile], active: Option<&profiles::Profile>) {
+    let Some((remotes, path)) = current_repo() else {
+        return;
+    };
+
+    match (matching_profile(profiles, &remotes, &path), active) {
+        (Some((i, rule)), active) if active.map(|p| &p.id) != Some(&profiles[i].id) => {
+            println!(
+                "\n{} {}\nRun {} to use it in this repository",
+                stylize(
+                    format!("This repository matches the profile '{}':", profiles[i].id),
+                    "yellow"
+                ),
+                stylize(rule, "+dim"),
+                stylize("usr auto", "blue")
+            );
+        }
+        (None, Some(active)) if !active.rules.is_empty() => {
+            println!(
+                "\n{}",
+                stylize(
+                    format!("The active profile '{}' doesn't match this repository", active.id),
+                    "yellow"
+                )
+            );
+        }
+        _ => {}
+    }
+}
+
+/// Show the current active user profile or Fail if none is set. In a repository, it also warns if
+/// the active profile isn't the one matching the repository. With `json`, the profile is printed
+/// as JSON instead.
+pub fn view(json: bool) -> Result<()> {
+    let config = profiles::Config::load()?;
     let active_profile_id = get_active_profile_id();
 
     if let Some(id) = active_profile_id {
         let profile = config.profile.iter().find(|p| p.id == id);
         if let Some(p) = profile {
+            if json {
+                println!("{}", serde_json::to_string_pretty(p)?);
+                return Ok(());
+            }
+
             println!("{}{} {}", stylize("Name", "+bold"), stylize(":", "white"), p.name);
             println!("{}{} {}", stylize("Email", "+bold"), stylize(":", "white"), p.email);
             if let Some(key) = &p.signingkey {
                 println!("{}{} {}", stylize("Signing key", "+bold"), stylize(":", "white"), key);
             }
+            if let Some(format) = p.format {
+                println!("{}{} {}", stylize("Format", "+bold"), stylize(":", "white"), format);
+            }
+            for (key, value) in &p.config {
+                let value = value.values().join(", ");
+                println!("{}{} {}", stylize(key, "+bold"), stylize(":", "white"), value);
+            }
+            if let Err(reason) = signing::validate(p) {
+                println!("\n{} {}", stylize("Invalid signing settings:", "yellow"), reason);
+            }
 
+            check_rules(&config.profile, Some(p));
             return Ok(());
         }
     }
 
+    if !json {
+        check_rules(&config.profile, None);
+    }
+
     fail!(
         "{}\nUse {} to create a new profile and then {} to activate it",
         stylize("No active profile set", "red"),
@@ -86,9 +174,14 @@ pub fn view() -> Result<()> {
     )
 }
 
-/// Print the list of configured user profiles in a table format.
-pub fn list() -> Result<()> {
-    let config = profiles::Config::load();
+/// Print the list of configured user profiles in a table format, or as JSON if `json` is true.
+pub fn list(json: bool) -> Result<()> {
+    let config = profiles::Config::load()?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&config.profile)?);
+        return Ok(());
+    }
 
     let data = config.profile;
     let len = data.len();
@@ -115,108 +208,756 @@ pub fn list() -> Result<()> {
     Ok(())
 }
 
-/// Add a new user profile to the `gusr.toml` file using a series of prompts asking for:
+/// Add a new user profile to the `gusr.toml` file. With `--name` and `--email` (and optionally
+/// `--id` and `--key`), the profile is added as given; otherwise a series of prompts asks for:
 /// - Id (optional) -> Generate a random ID as a default
 /// - Name (required)
 /// - Email (required)
 /// - Signing key (optional)
-pub fn add() -> Result<()> {
-    inquire::set_global_render_config(inquire_cfg());
-    let mut config = profiles::Config::load();
+pub fn add(args: ProfileArgs) -> Result<()> {
+    let mut config = profiles::Config::load()?;
+
+    let profile = match (args.name, args.email) {
+        (Some(name), Some(email)) => profiles::Profile {
+            id: args.id.unwrap_or_else(|| unique_id(&config)),
+            name,
+            email,
+            signingkey: args.key.filter(|key| !key.is_empty()),
+            format: None,
+            gpgsign: None,
+            tagsign: None,
+            program: None,
+            allowedsigners: None,
+            rules: Vec::new(),
+            config: Default::default(),
+        },
+        (None, None) if args.key.is_none() => prompt_profile(&config, args.id, None)?,
+        _ => {
+            return fail!(
+                "{}",
+                stylize("Both --name and --email are required to add a profile", "red")
+            )
+        }
+    };
 
-    // generate a random id until it's unique in the config
-    let mut random_id;
+    if config.profile.iter().any(|p| p.id == profile.id) {
+        return fail!("{}", stylize(format!("Profile '{}' already exists", profile.id), "red"));
+    }
+
+    let added = format!("Profile added: {} <{}>", profile.name, profile.email);
+    config.profile.push(profile);
+    config.save()?;
+
+    println!("{added}");
+    Ok(())
+}
+
+/// A random ID that isn't used by any profile
+fn unique_id(config: &profiles::Config) -> String {
     loop {
-        random_id = generate_random_id(4);
-        if !config.profile.iter().any(|p| p.id == random_id) {
-            break;
+        let id = generate_random_id(4);
+        if !config.profile.iter().any(|p| p.id == id) {
+            return id;
         }
     }
+}
+
+/// Ask for the fields of a profile: a new one, or `current` with its fields prefilled
+fn prompt_profile(
+    config: &profiles::Config,
+    id: Option<String>,
+    current: Option<&profiles::Profile>,
+) -> Result<profiles::Profile> {
+    inquire::set_global_render_config(inquire_cfg());
 
-    let id = Text::new("id").with_default(&random_id).prompt_skippable()?;
+    let default_id = match (id, current) {
+        (Some(id), _) => id,
+        (None, Some(current)) => current.id.clone(),
+        (None, None) => unique_id(config),
+    };
+    let taken: Vec<String> = config
+        .profile
+        .iter()
+        .map(|p| p.id.clone())
+        .filter(|id| current.is_none_or(|current| current.id != *id))
+        .collect();
+    let id = Text::new("id")
+        .with_default(&default_id)
+        .with_validator(unused_id(taken))
+        .prompt_skippable()?;
+
+    let name = Text::new("name")
+        .with_initial_value(current.map_or("", |p| &p.name))
+        .with_validator(required!("Name is required"))
+        .prompt()?;
+    let email = Text::new("email")
+        .with_initial_value(current.map_or("", |p| &p.email))
+        .with_validator(|email: &str| {
+            Ok(match profiles::check_email(email) {
+                Ok(()) => Validation::Valid,
+                Err(reason) => Validation::Invalid(reason.into()),
+            })
+        })
+        .prompt()?;
+    let signingkey = Text::new("key")
+        .with_initial_value(current.and_then(|p| p.signingkey.as_deref()).unwrap_or_default())
+        .with_help_message("gpg key id, ssh key path or literal ssh key; empty for none")
+        .prompt_skippable()?
+        .filter(|key| !key.is_empty());
+
+    let formats = vec![Format::Openpgp, Format::Ssh, Format::X509];
+    let current_format = current.and_then(|p| p.format).unwrap_or_default();
+    let (format, gpgsign) = match &signingkey {
+        Some(_) => (
+            Some(
+                Select::new("format", formats.clone())
+                    .with_starting_cursor(
+                        formats.iter().position(|f| *f == current_format).unwrap_or(0),
+                    )
+                    .without_help_message()
+                    .prompt()?,
+            ),
+            Some(
+                Confirm::new("sign commits and tags")
+                    .with_default(current.and_then(|p| p.gpgsign).unwrap_or(true))
+                    .prompt()?,
+            ),
+        ),
+        None => (None, None),
+    };
+    let rules = Text::new("match")
+        .with_initial_value(&current.map(|p| p.rules.join(", ")).unwrap_or_default())
+        .with_help_message("comma-separated remotes or paths, e.g. github.com/acme/*, ~/work/**")
+        .prompt_skippable()?;
 
-    let name = Text::new("name").with_validator(required!("Name is required")).prompt()?;
-    let email = Text::new("email").with_validator(required!("Email is required")).prompt()?;
-    let signingkey = Text::new("key").prompt_skippable()?;
+    // the settings the form doesn't ask for are kept
+    let (program, allowedsigners, config) = match current {
+        Some(p) => (p.program.clone(), p.allowedsigners.clone(), p.config.clone()),
+        None => (None, None, Default::default()),
+    };
+    let tagsign = match current {
+        Some(p) if p.gpgsign == gpgsign => p.tagsign,
+        _ => gpgsign,
+    };
 
-    let id = id.unwrap_or_else(|| generate_random_id(8));
-    let profile = profiles::Profile {
-        id,
+    Ok(profiles::Profile {
+        id: id.unwrap_or(default_id),
         name,
         email,
-        signingkey: signingkey.filter(|s| !s.is_empty()),
-    };
+        signingkey,
+        format,
+        gpgsign,
+        tagsign,
+        program,
+        allowedsigners,
+        config,
+        rules: rules
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|rule| !rule.is_empty())
+            .map(String::from)
+            .collect(),
+    })
+}
 
-    config.profile.push(profile);
-    config.save();
-    Ok(())
+/// A prompt validator refusing the ids in `taken`
+fn unused_id(taken: Vec<String>) -> impl StringValidator {
+    move |id: &str| {
+        Ok(match taken.iter().any(|taken| taken == id) {
+            true => Validation::Invalid(format!("Profile '{id}' already exists").into()),
+            false => Validation::Valid,
+        })
+    }
 }
 
-/// Remove a user profile from the `gusr.toml` file using a Select prompt to choose the profile to
-/// remove.
-pub fn remove() -> Result<()> {
-    let mut config = profiles::Config::load();
+/// Ask which profile to use
+fn select_profile(config: &profiles::Config, message: &str) -> Result<String> {
     let options = config.profile.iter().map(|p| p.id.clone()).collect::<Vec<_>>();
 
-    let ans = Select::new("Select a profile to remove", options)
+    let ans = Select::new(message, options)
         .with_page_size(10)
         .without_help_message()
         .with_render_config(inquire_cfg())
         .prompt();
 
     match ans {
-        Ok(choice) => {
-            config.profile.retain(|p| p.id != choice);
-            config.save();
-            Ok(())
-        }
+        Ok(choice) => Ok(choice),
         Err(err) => fail!("{}", stylize(err.to_string(), "red")),
     }
 }
 
-/// Set the active user profile by choosing from a list of profiles.
+/// Remove a user profile from the `gusr.toml` file: the given one (after confirmation, unless
+/// `yes`), or the one chosen in a Select prompt.
+pub fn remove(id: Option<String>, yes: bool) -> Result<()> {
+    let mut config = profiles::Config::load()?;
+
+    let choice = match id {
+        Some(id) => {
+            find_profile(&config, &id)?;
+
+            let confirmed = yes || {
+                let ans = Confirm::new(&format!("Remove the profile '{id}'?"))
+                    .with_default(false)
+                    .with_render_config(inquire_cfg())
+                    .prompt();
+
+                match ans {
+                    Ok(confirmed) => confirmed,
+                    Err(err) => {
+                        return fail!(
+                            "{}\nUse {} to skip the confirmation",
+                            stylize(err.to_string(), "red"),
+                            stylize("--yes", "yellow")
+                        )
+                    }
+                }
+            };
+
+            if !confirmed {
+                println!("Aborted.");
+                return Ok(());
+            }
+
+            id
+        }
+        None => select_profile(&config, "Select a profile to remove")?,
+    };
+
+    config.profile.retain(|p| p.id != choice);
+    config.save()?;
+    Ok(())
+}
+
+/// Edit a profile (the chosen one if no id is given): in a form prefilled with its fields, or
+/// changing only the given ones (an empty `--key` removes the signing key)
+pub fn edit(id: Option<String>, changes: ProfileArgs) -> Result<()> {
+    let mut config = profiles::Config::load()?;
+
+    let id = match id {
+        Some(id) => id,
+        None => select_profile(&config, "Select a profile to edit")?,
+    };
+    let Some(index) = config.profile.iter().position(|p| p.id == id) else {
+        return fail!("{}", stylize(format!("Profile '{id}' not found"), "red"));
+    };
+
+    let profile = match changes.is_empty() {
+        true => prompt_profile(&config, None, Some(&config.profile[index]))?,
+        false => {
+            let mut profile = config.profile[index].clone();
+
+            if let Some(new_id) = changes.id {
+                profile.id = new_id;
+            }
+            if let Some(name) = changes.name {
+                profile.name = name;
+            }
+            if let Some(email) = changes.email {
+                profile.email = email;
+            }
+            if let Some(key) = changes.key {
+                profile.signingkey = Some(key).filter(|key| !key.is_empty());
+            }
+
+            profile
+        }
+    };
+
+    if profile.id != id && config.profile.iter().any(|p| p.id == profile.id) {
+        return fail!("{}", stylize(format!("Profile '{}' already exists", profile.id), "red"));
+    }
+
+    let updated = format!("Profile updated: {} <{}>", profile.name, profile.email);
+    let new_id = profile.id.clone();
+    config.profile[index] = profile;
+    config.save()?;
+
+    println!("{updated}");
+    if get_active_profile_id().as_deref() == Some(id.as_str()) {
+        println!(
+            "{} {}",
+            stylize("It's the active profile, run", "yellow"),
+            stylize(format!("usr set {new_id}"), "blue")
+        );
+    }
+
+    Ok(())
+}
+
+/// Restore the profiles as they were before the last change
+pub fn undo() -> Result<()> {
+    match profiles::undo()? {
+        Some(config) => {
+            let ids = config.profile.iter().map(|p| p.id.as_str()).collect::<Vec<_>>();
+            let ids = match ids.is_empty() {
+                true => stylize("no profiles", "+dim"),
+                false => ids.join(", "),
+            };
+            println!(
+                "{} {}",
+                stylize("Restored the profiles before the last change:", "green"),
+                ids
+            );
+        }
+        None => println!("{}", stylize("Nothing to undo", "yellow")),
+    }
+
+    Ok(())
+}
+
+/// The profile with the given id, failing if there is none
+fn find_profile<'a>(config: &'a profiles::Config, id: &str) -> Result<&'a profiles::Profile> {
+    match config.profile.iter().find(|p| p.id == id) {
+        Some(profile) => Ok(profile),
+        None => fail!(
+            "{}\n{}",
+            stylize(format!("Profile '{id}' not found"), "red"),
+            stylize("Try `usr list` to see all profiles", "yellow")
+        ),
+    }
+}
+
+/// Where the git configuration of a profile is written
+pub enum Scope {
+    /// `~/.gitconfig`
+    Global,
+    /// the config of the current repository
+    Local,
+    /// a standalone file, included from the global config (see [`bind`])
+    File(PathBuf),
+}
+
+impl Scope {
+    /// The `git config` arguments selecting the scope
+    fn args(&self) -> Vec<String> {
+        match self {
+            Scope::Global => vec![s!("--global")],
+            Scope::Local => vec![s!("--local")],
+            Scope::File(path) => vec![s!("--file"), path.to_string_lossy().to_string()],
+        }
+    }
+
+    /// Whether the values of the global config apply too, unless overridden
+    fn inherits(&self) -> bool {
+        !matches!(self, Scope::Global)
+    }
+}
+
+/// Write the `user.*`, signing and extra settings of a profile (and `gusr.active`) to the given
+/// scope, unsetting the signing settings the profile doesn't have (or disabling signing, in the
+/// scopes that inherit the global config).
+///
+/// The extra keys are recorded in `gusr.keys`, so they are removed when another profile is
+/// applied to the same scope, even if the profile they came from no longer exists. The values they
+/// replaced are kept in `gusr.saved` (as `<key>\n<value>`) and restored at that point.
+fn apply(profile: &profiles::Profile, scope: &Scope) -> Result<()> {
+    if let Err(reason) = signing::validate(profile) {
+        return fail!(
+            "{}: {}",
+            stylize(format!("Invalid profile '{}'", profile.id), "red"),
+            reason
+        );
+    }
+
+    let config = |args: &[&str]| {
+        let mut config_args = scope.args();
+        config_args.extend(args.iter().map(|arg| s!(*arg)));
+        git::exec("config", config_args.iter().map(String::as_str).collect())
+    };
+
+    config(&["gusr.active", &profile.id])?;
+    config(&["user.name", &profile.name])?;
+    config(&["user.email", &profile.email])?;
+
+    for (key, value) in signing::git_config(profile, scope.inherits()) {
+        match value {
+            Some(value) => config(&[key, &value])?,
+            // fails if it wasn't set, which is fine
+            None => config(&["--unset", key]).unwrap_or_default(),
+        };
+    }
+
+    let previous = config(&["--get-all", "gusr.keys"]).unwrap_or_default();
+    for key in previous.lines() {
+        config(&["--unset-all", key]).unwrap_or_default();
+    }
+    config(&["--unset-all", "gusr.keys"]).unwrap_or_default();
+
+    // values can have newlines, hence `--null`
+    let saved = config(&["--null", "--get-all", "gusr.saved"]).unwrap_or_default();
+    for (key, value) in saved.split_terminator('\0').filter_map(|entry| entry.split_once('\n')) {
+        config(&["--add", key, value])?;
+    }
+    config(&["--unset-all", "gusr.saved"]).unwrap_or_default();
+
+    for (key, value) in &profile.config {
+        // keep the values the key already had in this scope, to restore them later
+        let existing = config(&["--null", "--get-all", key]).unwrap_or_default();
+        for value in existing.split_terminator('\0') {
+            config(&["--add", "gusr.saved", &format!("{key}\n{value}")])?;
+        }
+
+        config(&["--unset-all", key]).unwrap_or_default();
+        for value in value.values() {
+            config(&["--add", key, &value])?;
+        }
+        config(&["--add", "gusr.keys", key])?;
+    }
+
+    Ok(())
+}
+
+/// Set the active user profile: the given one, or the one chosen from a list of profiles.
 /// The selected profile is then set as the active profile by setting the `gusr.active` git
-/// configuration globally.
+/// configuration globally, or only in the current repository if `local` is true.
 ///
 /// Also sets the user.name, user.email, and user.signingkey to match the selected profile.
-pub fn set() -> Result<()> {
-    let config = profiles::Config::load();
-    let options = config.profile.iter().map(|p| p.id.clone()).collect::<Vec<_>>();
+pub fn set(id: Option<String>, local: bool) -> Result<()> {
+    if local && git::exec("rev-parse", vec!["--git-dir"]).is_err() {
+        return fail!("{}", stylize("--local can only be used inside a git repository", "red"));
+    }
 
-    let ans = Select::new("Select a profile to set as active", options)
-        .with_page_size(10)
+    let config = profiles::Config::load()?;
+
+    let choice = match id {
+        Some(id) => id,
+        None => select_profile(&config, "Select a profile to set as active")?,
+    };
+
+    let p = find_profile(&config, &choice)?;
+    apply(p, if local { &Scope::Local } else { &Scope::Global })?;
+
+    let scope = if local { " for this repository" } else { "" };
+    println!("Active profile set{}: {} <{}>", scope, p.name, p.email);
+
+    Ok(())
+}
+
+/// Apply the profile whose rules match the repository in the current directory to the repository
+/// only (like `usr set --local`).
+pub fn auto() -> Result<()> {
+    let Some((remotes, path)) = current_repo() else {
+        return fail!("{}", stylize("Not in a git repository", "red"));
+    };
+
+    let config = profiles::Config::load()?;
+
+    let Some((i, rule)) = matching_profile(&config.profile, &remotes, &path) else {
+        return fail!(
+            "{}\nAdd {} rules to a profile (see {})",
+            stylize("No profile matches this repository", "red"),
+            stylize("match = [...]", "yellow"),
+            stylize("usr cfg", "blue")
+        );
+    };
+
+    let profile = &config.profile[i];
+    apply(profile, &Scope::Local)?;
+
+    println!(
+        "Active profile set for this repository: {} <{}> {}",
+        profile.name,
+        profile.email,
+        stylize(format!("({rule})"), "+dim")
+    );
+    Ok(())
+}
+
+/// Fail if the identity git commits with doesn't match the profile whose rules match the
+/// repository in the current directory. Used by the hook of `usr guard install`.
+pub fn check() -> Result<()> {
+    let Some((remotes, path)) = current_repo() else {
+        return Ok(());
+    };
+
+    let config = profiles::Config::load()?;
+
+    let Some((i, rule)) = matching_profile(&config.profile, &remotes, &path) else {
+        return Ok(());
+    };
+
+    // the author git would use, taking the env vars (GIT_AUTHOR_EMAIL...) into account
+    let ident = git::exec("var", vec!["GIT_AUTHOR_IDENT"]).unwrap_or_default();
+    let email = ident.split_once('<').and_then(|(_, rest)| rest.split_once('>')).map(|(e, _)| e);
+
+    let profile = &config.profile[i];
+    if email == Some(profile.email.as_str()) {
+        return Ok(());
+    }
+
+    fail!(
+        "{}\nThis repository should use the profile '{}' <{}> {}\nRun {} to use it",
+        stylize(format!("Committing as <{}>", email.unwrap_or("unknown")), "red"),
+        profile.id,
+        profile.email,
+        stylize(format!("({rule})"), "+dim"),
+        stylize("usr auto", "blue")
+    )
+}
+
+/// Write the given profiles (all of them if none is given) to a bundle (see [`crate::bundle`]), in
+/// a file or to stdout
+pub fn export(ids: Vec<String>, json: bool, keys: bool, output: Option<String>) -> Result<()> {
+    let config = profiles::Config::load()?;
+
+    let profiles = match ids.is_empty() {
+        true => config.profile.clone(),
+        false => {
+            ids.iter().map(|id| find_profile(&config, id).cloned()).collect::<Result<Vec<_>>>()?
+        }
+    };
+
+    let json = json || output.as_deref().is_some_and(|file| bundle::is_json(Path::new(file)));
+    let content = format!("{}\n", bundle::export(profiles, keys, json)?.trim_end());
+
+    let Some(file) = output else {
+        print!("{content}");
+        return Ok(());
+    };
+
+    if fs::write(&file, content).is_err() {
+        return fail!("{} {}", stylize("Could not write", "red"), file);
+    }
+
+    let count = if ids.is_empty() {
+        config.profile.len()
+    } else {
+        ids.len()
+    };
+    println!("Exported {count} profile(s) to {file}");
+    Ok(())
+}
+
+/// Add the profiles of a bundle, asking what to do with the ones whose id is already used unless
+/// `replace` or `skip` is given
+pub fn import(file: String, replace: bool, skip: bool) -> Result<()> {
+    let Ok(content) = fs::read_to_string(&file) else {
+        return fail!("{} {}", stylize("Could not read", "red"), file);
+    };
+
+    let bundle = bundle::parse(&content, bundle::is_json(Path::new(&file)))?;
+    let mut config = profiles::Config::load()?;
+
+    let summary = bundle::merge(&mut config, bundle, |config, profile| match (replace, skip) {
+        (true, _) => Ok(Resolution::Replace),
+        (_, true) => Ok(Resolution::Skip),
+        _ => resolve_collision(config, profile),
+    })?;
+    config.save()?;
+
+    println!(
+        "Imported {}: {} added, {} replaced, {} skipped",
+        file, summary.added, summary.replaced, summary.skipped
+    );
+    if summary.added + summary.replaced > 0 {
+        println!(
+            "{} {} {}",
+            stylize("Run", "+dim"),
+            stylize("usr undo", "blue"),
+            stylize("to revert it", "+dim")
+        );
+    }
+
+    Ok(())
+}
+
+/// Ask what to do with an imported profile whose id is already used
+fn resolve_collision(
+    config: &profiles::Config,
+    imported: &profiles::Profile,
+) -> Result<Resolution> {
+    const REPLACE: &str = "Replace it";
+    const SKIP: &str = "Keep the existing one";
+    const RENAME: &str = "Import it with another id";
+
+    let existing = find_profile(config, &imported.id)?;
+    let message = format!(
+        "Profile '{}' already exists: {} <{}> (importing {} <{}>)",
+        imported.id, existing.name, existing.email, imported.name, imported.email
+    );
+
+    let ans = Select::new(&message, vec![REPLACE, SKIP, RENAME])
         .without_help_message()
         .with_render_config(inquire_cfg())
         .prompt();
 
-    match ans {
-        Ok(choice) => {
-            let profile = config.profile.iter().find(|p| p.id == choice);
-
-            match profile {
-                Some(p) => {
-                    git::exec("config", vec!["--global", "gusr.active", &p.id])?;
-                    git::exec("config", vec!["--global", "user.name", &p.name])?;
-                    git::exec("config", vec!["--global", "user.email", &p.email])?;
-                    if let Some(key) = &p.signingkey {
-                        git::exec("config", vec!["--global", "user.signingkey", key])?;
-                    } else {
-                        git::exec("config", vec!["--global", "--unset", "user.signingkey"])?;
-                    }
+    let choice = match ans {
+        Ok(choice) => choice,
+        Err(err) => {
+            return fail!(
+                "{}\nUse {} or {} to resolve the collisions without prompts",
+                stylize(err.to_string(), "red"),
+                stylize("--replace", "yellow"),
+                stylize("--skip", "yellow")
+            )
+        }
+    };
 
-                    println!("Active profile set to: {} <{}>", p.name, p.email);
-                }
-                None => {
-                    return fail!(
-                        "{}\n{}",
-                        stylize("Profile not found", "red"),
-                        stylize("Try `usr list` to see all profiles", "yellow")
-                    );
-                }
-            }
+    Ok(match choice {
+        REPLACE => Resolution::Replace,
+        SKIP => Resolution::Skip,
+        _ => {
+            let taken = config.profile.iter().map(|p| p.id.clone()).collect();
+            let id = Text::new("new id")
+                .with_default(&unique_id(config))
+                .with_validator(unused_id(taken))
+                .with_render_config(inquire_cfg())
+                .prompt()?;
 
-            Ok(())
+            Resolution::Rename(id)
         }
-        Err(err) => fail!("{}", stylize(err.to_string(), "red")),
+    })
+}
+
+/// Rewrite the author and committer of recent commits (see [`crate::fix`]) with the given
+/// profile, the one matching the repository or the one chosen from a list
+pub fn fix(range: Option<String>, id: Option<String>, force: bool, yes: bool) -> Result<()> {
+    let Some((remotes, path)) = current_repo() else {
+        return fail!("{}", stylize("Not in a git repository", "red"));
+    };
+
+    let config = profiles::Config::load()?;
+
+    let id = match (id, matching_profile(&config.profile, &remotes, &path)) {
+        (Some(id), _) => id,
+        (None, Some((i, _))) => config.profile[i].id.clone(),
+        (None, None) => select_profile(&config, "Select the profile to rewrite the commits with")?,
+    };
+
+    crate::fix::run(find_profile(&config, &id)?, range, force, yes)
+}
+
+/// Bind a profile to a directory: the profile is written to its own include file, and an
+/// `includeIf "gitdir:<dir>/"` entry pointing to it is added to the global config, so every
+/// repository under the directory uses the profile without switching.
+pub fn bind(id: String, dir: String) -> Result<()> {
+    let config = profiles::Config::load()?;
+
+    let Some(profile) = config.profile.iter().find(|p| p.id == id) else {
+        return fail!(
+            "{}\n{}",
+            stylize(format!("Profile '{id}' not found"), "red"),
+            stylize("Try `usr list` to see all profiles", "yellow")
+        );
+    };
+
+    let dir = match fs::canonicalize(&dir) {
+        Ok(dir) => dir.to_string_lossy().to_string(),
+        Err(_) => return fail!("{}", stylize(format!("Directory '{dir}' not found"), "red")),
+    };
+
+    let include = config.include_path(&profile.id);
+    if let Some(parent) = include.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // start from scratch, so settings removed from the profile don't linger
+    if include.exists() {
+        fs::remove_file(&include)?;
+    }
+    apply(profile, &Scope::File(include.clone()))?;
+
+    let key = format!("includeIf.gitdir:{}/.path", dir.trim_end_matches('/'));
+    let include = include.to_string_lossy().to_string();
+
+    if let Ok(previous) = git::exec("config", vec!["--global", "--get", &key]) {
+        if previous.trim() != include {
+            println!(
+                "{} {}",
+                stylize("Replacing the previous binding:", "yellow"),
+                previous.trim()
+            );
+        }
+    }
+
+    git::exec("config", vec!["--global", &key, &include])?;
+
+    println!("Bound {} <{}> to {}/", profile.name, profile.email, dir.trim_end_matches('/'));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{apply, Scope},
+        crate::profiles::Profile,
+        common::git,
+        std::{env, fs},
+    };
+
+    #[test]
+    fn test_apply_unsigned_under_signing_global() {
+        let dir = env::temp_dir().join(format!("gusr-apply-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (global, included) = (dir.join("global"), dir.join("included"));
+        let path = global.to_string_lossy().to_string();
+        let get = |key| {
+            let args = vec!["--file", &path, "--includes", "--get", key];
+            git::exec("config", args).unwrap_or_default()
+        };
+
+        let home: Profile = toml::from_str(
+            r#"
+            id = "home"
+            name = "Me"
+            email = "me@home"
+            signingkey = "ABCDEF"
+            gpgsign = true
+            tagsign = true
+            "#,
+        )
+        .unwrap();
+        let work: Profile =
+            toml::from_str("id = \"work\"\nname = \"Worker\"\nemail = \"w@corp\"").unwrap();
+
+        // like `usr set home` and then `usr set --local work`: the included file inherits the
+        // signing settings of the file including it the same way a repository inherits the global
+        // config
+        apply(&home, &Scope::File(global.clone())).unwrap();
+        let include = included.to_string_lossy().to_string();
+        git::exec("config", vec!["--file", &path, "include.path", &include]).unwrap();
+        apply(&work, &Scope::File(included)).unwrap();
+
+        assert_eq!(get("user.email").trim(), "w@corp");
+        assert_eq!(get("commit.gpgsign").trim(), "false");
+        assert_eq!(get("tag.gpgsign").trim(), "false");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_restores_replaced_values() {
+        let dir = env::temp_dir().join(format!("gusr-restore-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("config");
+        let path = file.to_string_lossy().to_string();
+        let get = |key| git::exec("config", vec!["--file", &path, "--get-all", key]);
+
+        git::exec("config", vec!["--file", &path, "credential.helper", "osxkeychain"]).unwrap();
+
+        let work: Profile = toml::from_str(
+            r#"
+            id = "work"
+            name = "Worker"
+            email = "w@corp"
+
+            [config]
+            "credential.helper" = "store"
+            "#,
+        )
+        .unwrap();
+        let home: Profile =
+            toml::from_str("id = \"home\"\nname = \"Me\"\nemail = \"me@home\"").unwrap();
+
+        apply(&work, &Scope::File(file.clone())).unwrap();
+        assert_eq!(get("credential.helper").unwrap().trim(), "store");
+
+        // switching again keeps the original value saved, not the profile's
+        apply(&work, &Scope::File(file.clone())).unwrap();
+        apply(&home, &Scope::File(file.clone())).unwrap();
+        assert_eq!(get("credential.helper").unwrap().trim(), "osxkeychain");
+        assert!(get("gusr.saved").is_err());
+
+        fs::remove_dir_all(dir).unwrap();
     }
 }
@@ -1,21 +1,94 @@
 use {
+    crate::guard::HOOKS,
     eyre::Result,
-    lool::{cli::stylize::stylize, fail},
+    lool::{cli::stylize::stylize, fail, s},
+    pico_args::Arguments,
 };
 
+/// Fields of a profile given as flags (`--id`, `--name`, `--email`, `--key`)
+#[derive(Default)]
+pub struct ProfileArgs {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub key: Option<String>,
+}
+
+impl ProfileArgs {
+    fn parse(pargs: &mut Arguments) -> Result<Self> {
+        let mut value = |flag| match pargs.opt_value_from_str::<_, String>(flag) {
+            Ok(value) => Ok(value),
+            Err(_) => usage(),
+        };
+
+        Ok(ProfileArgs {
+            id: value("--id")?,
+            name: value("--name")?,
+            email: value("--email")?,
+            key: value("--key")?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.id.is_none() && self.name.is_none() && self.email.is_none() && self.key.is_none()
+    }
+}
+
 pub enum Action {
     Version,
     Help,
-    List,
-    Add,
-    Remove,
-    Set,
-    View,
+    List {
+        json: bool,
+    },
+    Add(ProfileArgs),
+    Edit {
+        id: Option<String>,
+        changes: ProfileArgs,
+    },
+    Remove {
+        id: Option<String>,
+        yes: bool,
+    },
+    Set {
+        id: Option<String>,
+        local: bool,
+    },
+    Bind {
+        id: String,
+        dir: String,
+    },
+    Auto,
+    Check,
+    Guard {
+        hook: String,
+        global: bool,
+    },
+    Undo,
+    Export {
+        ids: Vec<String>,
+        json: bool,
+        keys: bool,
+        output: Option<String>,
+    },
+    Import {
+        file: String,
+        replace: bool,
+        skip: bool,
+    },
+    Fix {
+        range: Option<String>,
+        id: Option<String>,
+        force: bool,
+        yes: bool,
+    },
+    View {
+        json: bool,
+    },
     ShowConfig,
 }
 
 pub fn parse_args() -> Result<Action> {
-    let mut pargs = pico_args::Arguments::from_env();
+    let mut pargs = Arguments::from_env();
 
     if pargs.contains(["-h", "--help"]) {
         return Ok(Action::Help);
@@ -29,18 +102,145 @@ pub fn parse_args() -> Result<Action> {
 
     match maybe_subcmd {
         Ok(Some(subcmd)) => match subcmd.as_str() {
-            "list" => Ok(Action::List),
-            "add" => Ok(Action::Add),
-            "rm" => Ok(Action::Remove),
-            "set" => Ok(Action::Set),
+            "list" => Ok(Action::List {
+                json: pargs.contains("--json"),
+            }),
+            "add" => Ok(Action::Add(ProfileArgs::parse(&mut pargs)?)),
+            "edit" => parse_edit(pargs),
+            "rm" => Ok(Action::Remove {
+                yes: pargs.contains(["-y", "--yes"]),
+                id: pargs.opt_free_from_str()?,
+            }),
+            "set" => Ok(Action::Set {
+                local: pargs.contains("--local"),
+                id: pargs.opt_free_from_str()?,
+            }),
+            "bind" => parse_bind(pargs),
+            "auto" => Ok(Action::Auto),
+            "check" => Ok(Action::Check),
+            "guard" => parse_guard(pargs),
+            "undo" => Ok(Action::Undo),
+            "fix" => parse_fix(pargs),
+            "export" => parse_export(pargs),
+            "import" => parse_import(pargs),
             "cfg" => Ok(Action::ShowConfig),
             _ => f(),
         },
-        Ok(None) => Ok(Action::View),
+        Ok(None) => Ok(Action::View {
+            json: pargs.contains("--json"),
+        }),
         _ => f(),
     }
 }
 
+/// `usr edit [<id>]` (form) or `usr edit <id> [--id <new-id>] [--name <name>] [--email <email>]
+/// [--key <key>]`
+fn parse_edit(mut pargs: Arguments) -> Result<Action> {
+    let changes = ProfileArgs::parse(&mut pargs)?;
+
+    match pargs.opt_free_from_str() {
+        Ok(id) if id.is_some() || changes.is_empty() => Ok(Action::Edit { id, changes }),
+        _ => usage(),
+    }
+}
+
+/// `usr fix [<range>] [--profile <id>] [--force] [--yes]`
+fn parse_fix(mut pargs: Arguments) -> Result<Action> {
+    let Ok(id) = pargs.opt_value_from_str("--profile") else {
+        return usage();
+    };
+
+    Ok(Action::Fix {
+        id,
+        force: pargs.contains(["-f", "--force"]),
+        yes: pargs.contains(["-y", "--yes"]),
+        range: pargs.opt_free_from_str()?,
+    })
+}
+
+/// `usr export [--ids <id>,...] [--json] [--no-keys] [-o <file>]`
+fn parse_export(mut pargs: Arguments) -> Result<Action> {
+    let (Ok(ids), Ok(output)) = (
+        pargs.values_from_str::<_, String>("--ids"),
+        pargs.opt_value_from_str(["-o", "--output"]),
+    ) else {
+        return usage();
+    };
+
+    Ok(Action::Export {
+        ids: ids
+            .iter()
+            .flat_map(|ids| ids.split(','))
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(String::from)
+            .collect(),
+        json: pargs.contains("--json"),
+        keys: !pargs.contains("--no-keys"),
+        output,
+    })
+}
+
+/// `usr import <file> [--replace | --skip]`
+fn parse_import(mut pargs: Arguments) -> Result<Action> {
+    let replace = pargs.contains("--replace");
+    let skip = pargs.contains("--skip");
+
+    match pargs.free_from_str() {
+        Ok(file) if !(replace && skip) => Ok(Action::Import {
+            file,
+            replace,
+            skip,
+        }),
+        _ => usage(),
+    }
+}
+
+fn usage<T>() -> Result<T> {
+    fail!(
+        "{}\nTry {} for more information",
+        stylize("Bad Usage", "red"),
+        stylize("usr --help", "yellow+italic")
+    )
+}
+
+/// `usr bind <profile> <dir>`
+fn parse_bind(mut pargs: Arguments) -> Result<Action> {
+    let (Ok(id), Ok(dir)) = (pargs.free_from_str(), pargs.free_from_str()) else {
+        return fail!(
+            "{}\nTry {} for more information",
+            stylize("Usage: usr bind <profile> <dir>", "red"),
+            stylize("usr --help", "yellow+italic")
+        );
+    };
+
+    Ok(Action::Bind { id, dir })
+}
+
+/// `usr guard install [--global] [--hook pre-commit|commit-msg]`
+fn parse_guard(mut pargs: Arguments) -> Result<Action> {
+    let usage = || {
+        fail!(
+            "{}\nTry {} for more information",
+            stylize("Usage: usr guard install [--global] [--hook pre-commit|commit-msg]", "red"),
+            stylize("usr --help", "yellow+italic")
+        )
+    };
+
+    let global = pargs.contains("--global");
+    let hook = match pargs.opt_value_from_str::<_, String>("--hook") {
+        Ok(hook) => hook.unwrap_or_else(|| s!(HOOKS[0])),
+        Err(_) => return usage(),
+    };
+
+    match pargs.subcommand() {
+        Ok(Some(subcmd)) if subcmd == "install" && HOOKS.contains(&hook.as_str()) => {
+            Ok(Action::Guard { hook, global })
+        }
+        _ => usage(),
+    }
+}
+
 fn f() -> Result<Action> {
     fail!(
         "{}\nTry {} for more information",
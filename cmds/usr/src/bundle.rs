@@ -0,0 +1,200 @@
+//! # usr/bundle
+//!
+//! Profiles exported with `usr export` to share them between machines or with a team. A bundle has
+//! the same `[[profile]]` format as `gusr.toml`, as TOML or as JSON (`{"profile": [...]}`), and can
+//! leave out the signing keys, which are usually personal.
+//!
+//! `usr import <file>` adds the profiles of a bundle. When a profile with the same id already
+//! exists (and isn't identical), the imported one can replace it, be skipped, or be added with
+//! another id.
+
+use {
+    crate::profiles::{Config, Profile},
+    eyre::Result,
+    lool::{cli::stylize::stylize, fail},
+    std::path::Path,
+};
+
+/// What to do with an imported profile whose id is already used
+pub enum Resolution {
+    Replace,
+    Skip,
+    Rename(String),
+}
+
+/// What an import did
+#[derive(Default, Debug, PartialEq)]
+pub struct Summary {
+    pub added: usize,
+    pub replaced: usize,
+    pub skipped: usize,
+}
+
+/// Whether a bundle file is JSON (by its extension) rather than TOML
+pub fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
+
+/// The bundle of the given profiles, without any of their signing settings if `keys` is false
+pub fn export(profiles: Vec<Profile>, keys: bool, json: bool) -> Result<String> {
+    let profile = profiles
+        .into_iter()
+        .map(|mut profile| {
+            if !keys {
+                // signing without a key fails, and the program and allowed signers file are paths
+                // of this machine, so all the signing settings go along with it
+                profile.signingkey = None;
+                profile.format = None;
+                profile.gpgsign = None;
+                profile.tagsign = None;
+                profile.program = None;
+                profile.allowedsigners = None;
+            }
+            profile
+        })
+        .collect();
+
+    let bundle = Config {
+        path: None,
+        profile,
+    };
+
+    Ok(match json {
+        true => serde_json::to_string_pretty(&bundle)?,
+        false => toml::to_string_pretty(&bundle)?,
+    })
+}
+
+/// Read a bundle, failing if it can't be parsed or isn't valid
+pub fn parse(content: &str, json: bool) -> Result<Config> {
+    let bundle: Config = match json {
+        true => match serde_json::from_str(content) {
+            Ok(bundle) => bundle,
+            Err(err) => return fail!("{}\n{}", stylize("Could not parse the bundle", "red"), err),
+        },
+        false => match toml::from_str(content) {
+            Ok(bundle) => bundle,
+            Err(err) => return fail!("{}\n{}", stylize("Could not parse the bundle", "red"), err),
+        },
+    };
+
+    if let Err(reason) = bundle.validate() {
+        return fail!("{}: {}", stylize("Invalid bundle", "red"), reason);
+    }
+
+    Ok(bundle)
+}
+
+/// Add the profiles of the bundle to the config, asking `resolve` what to do with the ones whose id
+/// is already used. Profiles identical to an existing one are skipped without asking.
+pub fn merge(
+    config: &mut Config,
+    bundle: Config,
+    mut resolve: impl FnMut(&Config, &Profile) -> Result<Resolution>,
+) -> Result<Summary> {
+    let mut summary = Summary::default();
+
+    for mut profile in bundle.profile {
+        let Some(i) = config.profile.iter().position(|p| p.id == profile.id) else {
+            config.profile.push(profile);
+            summary.added += 1;
+            continue;
+        };
+
+        if same(&config.profile[i], &profile) {
+            summary.skipped += 1;
+            continue;
+        }
+
+        match resolve(config, &profile)? {
+            Resolution::Replace => {
+                config.profile[i] = profile;
+                summary.replaced += 1;
+            }
+            Resolution::Skip => summary.skipped += 1,
+            Resolution::Rename(id) => {
+                if config.profile.iter().any(|p| p.id == id) {
+                    return fail!("{}", stylize(format!("Profile '{id}' already exists"), "red"));
+                }
+                profile.id = id;
+                config.profile.push(profile);
+                summary.added += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+fn same(a: &Profile, b: &Profile) -> bool {
+    toml::to_string(a).ok() == toml::to_string(b).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export, merge, parse, Resolution, Summary};
+
+    const BUNDLE: &str = r#"
+        [[profile]]
+        id = "work"
+        name = "Worker"
+        email = "w@corp"
+        signingkey = "~/.ssh/id_ed25519.pub"
+        format = "ssh"
+        gpgsign = true
+        tagsign = true
+        program = "/usr/bin/ssh-keygen"
+        allowedsigners = "~/.ssh/allowed_signers"
+
+        [[profile]]
+        id = "home"
+        name = "Me"
+        email = "me@home"
+    "#;
+
+    #[test]
+    fn test_export_without_keys() {
+        let bundle = parse(BUNDLE, false).unwrap();
+        let exported = export(bundle.profile, false, true).unwrap();
+        let bundle = parse(&exported, true).unwrap();
+
+        assert_eq!(bundle.profile.len(), 2);
+        assert!(bundle.profile.iter().all(|p| {
+            p.signingkey.is_none()
+                && p.format.is_none()
+                && p.gpgsign.is_none()
+                && p.tagsign.is_none()
+                && p.program.is_none()
+                && p.allowedsigners.is_none()
+        }));
+        assert!(!exported.contains("ssh"));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut config = parse(BUNDLE, false).unwrap();
+
+        // work collides and differs, new is new and home is identical to the one in the config
+        let changed = BUNDLE.replace("Worker", "Boss").replace("\"home\"", "\"new\"");
+        let mut bundle = parse(&changed, false).unwrap();
+        bundle.profile.push(parse(BUNDLE, false).unwrap().profile.remove(1));
+
+        let summary = merge(&mut config, bundle, |_, profile| {
+            assert_eq!(profile.id, "work");
+            Ok(Resolution::Rename(String::from("boss")))
+        })
+        .unwrap();
+
+        assert_eq!(
+            summary,
+            Summary {
+                added: 2,
+                replaced: 0,
+                skipped: 1
+            }
+        );
+        let ids: Vec<&str> = config.profile.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["work", "home", "boss", "new"]);
+        assert_eq!(config.profile[2].name, "Boss");
+    }
+}
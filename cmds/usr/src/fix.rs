@@ -0,0 +1,295 @@
+//! # usr/fix
+//!
+//! `usr fix [<range>]` rewrites the author and committer of recent commits of the current branch
+//! to a profile, for when they were made with the wrong identity. It rebases the commits with an
+//! `--exec` amending each one with `--reset-author` (so the author date is reset too), with the
+//! identity and signing settings of the profile passed through the environment: the commits are
+//! signed again if the profile has a signing key.
+//!
+//! The range is `<base>..` (or just `<base>`): the commits after `<base>` up to `HEAD`. Without
+//! one, the commits since the branch forked from its upstream, or the ones that aren't on any
+//! remote-tracking branch if there is no upstream. Commits that are already on a remote-tracking
+//! branch are only rewritten with `--force`, since the new ones will need a force push.
+
+use {
+    crate::{profiles::Profile, signing},
+    common::git,
+    eyre::Result,
+    inquire::Confirm,
+    lool::{cli::stylize::stylize, fail, s},
+    std::{collections::HashSet, process::Command},
+};
+
+/// Amends each rebased commit; hooks were already run when the commits were made
+const EXEC: &str = "git commit --amend --no-edit --reset-author --allow-empty --no-verify --quiet";
+
+/// Where the rebase starts from
+enum Base {
+    Rev(String),
+    Root,
+}
+
+struct Commit {
+    sha: String,
+    short: String,
+    author: String,
+    subject: String,
+}
+
+pub fn run(profile: &Profile, range: Option<String>, force: bool, yes: bool) -> Result<()> {
+    if let Err(reason) = signing::validate(profile) {
+        return fail!(
+            "{}: {}",
+            stylize(format!("Invalid profile '{}'", profile.id), "red"),
+            reason
+        );
+    }
+
+    let Some(base) = base(range)? else {
+        println!("{}", stylize("No commits to fix", "yellow"));
+        return Ok(());
+    };
+
+    let commits = log(&base)?;
+    if commits.is_empty() {
+        println!("{}", stylize("No commits to fix", "yellow"));
+        return Ok(());
+    }
+
+    let unpushed: HashSet<String> = git::exec("rev-list", vec!["HEAD", "--not", "--remotes"])?
+        .lines()
+        .map(String::from)
+        .collect();
+    let pushed = commits.iter().filter(|commit| !unpushed.contains(&commit.sha)).count();
+
+    let signed = match profile.signingkey {
+        Some(_) => " (signed)",
+        None => "",
+    };
+    println!(
+        "{}",
+        stylize(
+            format!(
+                "Rewriting {} commit(s) as {} <{}>{}:",
+                commits.len(),
+                profile.name,
+                profile.email,
+                signed
+            ),
+            "cyan"
+        )
+    );
+    for commit in &commits {
+        let tag = match unpushed.contains(&commit.sha) {
+            true => s!(""),
+            false => format!(" {}", stylize("pushed", "red")),
+        };
+        println!(
+            "{}{} {} {}{}",
+            stylize("  │ ", "+dim"),
+            stylize(&commit.short, "yellow"),
+            commit.subject,
+            stylize(format!("({})", commit.author), "+dim"),
+            tag
+        );
+    }
+
+    if pushed > 0 && !force {
+        return fail!(
+            "{}\nUse {} to rewrite them anyway",
+            stylize(
+                format!(
+                    "{pushed} of them are already on a remote-tracking branch, rewriting them \
+                     needs a force push"
+                ),
+                "red"
+            ),
+            stylize("--force", "yellow")
+        );
+    }
+
+    let confirmed = yes || {
+        let ans = Confirm::new("Rewrite them?").with_default(true).prompt();
+
+        match ans {
+            Ok(confirmed) => confirmed,
+            Err(err) => {
+                return fail!(
+                    "{}\nUse {} to skip the confirmation",
+                    stylize(err.to_string(), "red"),
+                    stylize("--yes", "yellow")
+                )
+            }
+        }
+    };
+
+    if !confirmed {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    rebase(profile, &base)
+}
+
+/// Rebase the commits after `base`, amending each one with the identity and signing settings of
+/// the profile
+fn rebase(profile: &Profile, base: &Base) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.args(["rebase", "--quiet", "--rebase-merges", "--autostash", "--exec", EXEC]);
+    match base {
+        Base::Rev(rev) => cmd.arg(rev),
+        Base::Root => cmd.arg("--root"),
+    };
+
+    for var in ["GIT_AUTHOR_NAME", "GIT_COMMITTER_NAME"] {
+        cmd.env(var, &profile.name);
+    }
+    for var in ["GIT_AUTHOR_EMAIL", "GIT_COMMITTER_EMAIL"] {
+        cmd.env(var, &profile.email);
+    }
+
+    // passed as `GIT_CONFIG_KEY_<n>`/`GIT_CONFIG_VALUE_<n>` so they apply to the git commands run
+    // by the rebase without touching any config file
+    let settings = settings(profile);
+    cmd.env("GIT_CONFIG_COUNT", settings.len().to_string());
+    for (i, (key, value)) in settings.iter().enumerate() {
+        cmd.env(format!("GIT_CONFIG_KEY_{i}"), key);
+        cmd.env(format!("GIT_CONFIG_VALUE_{i}"), value);
+    }
+
+    if !cmd.status()?.success() {
+        return fail!(
+            "{}\nFix the problem and run {}, or run {} to go back",
+            stylize("The rebase stopped", "red"),
+            stylize("git rebase --continue", "yellow"),
+            stylize("git rebase --abort", "yellow")
+        );
+    }
+
+    println!("{}", stylize("Commits rewritten", "green"));
+    Ok(())
+}
+
+/// The signing settings of the profile; commits are signed if (and only if) it has a key
+fn settings(profile: &Profile) -> Vec<(String, String)> {
+    let mut settings: Vec<(String, String)> = signing::git_config(profile, false)
+        .into_iter()
+        .filter_map(|(key, value)| value.map(|value| (s!(key), value)))
+        .filter(|(key, _)| key != "commit.gpgsign" && key != "gpg.format")
+        .collect();
+
+    let sign = profile.signingkey.is_some();
+    settings.push((s!("commit.gpgsign"), sign.to_string()));
+    if sign {
+        settings.push((s!("gpg.format"), profile.format.unwrap_or_default().to_string()));
+    }
+
+    settings
+}
+
+/// The base of the commits to rewrite, `None` if there are none
+fn base(range: Option<String>) -> Result<Option<Base>> {
+    let Some(range) = range else {
+        return default_base();
+    };
+
+    let (base, end) = split_range(&range)?;
+
+    if !end.is_empty() && rev(end)? != rev("HEAD")? {
+        return fail!(
+            "{}",
+            stylize(
+                "Only commits of the current branch can be fixed, the range must end at HEAD",
+                "red"
+            )
+        );
+    }
+
+    let sha = rev(base)?;
+    if git::exec("merge-base", vec!["--is-ancestor", &sha, "HEAD"]).is_err() {
+        return fail!("{}", stylize(format!("'{base}' is not an ancestor of HEAD"), "red"));
+    }
+
+    Ok(Some(Base::Rev(sha)))
+}
+
+/// Where the branch forked from its upstream, or the parent of the oldest commit that isn't on
+/// any remote-tracking branch
+fn default_base() -> Result<Option<Base>> {
+    if rev("@{upstream}").is_ok() {
+        let fork = git::exec("merge-base", vec!["HEAD", "@{upstream}"])?;
+        return Ok(Some(Base::Rev(s!(fork.trim()))));
+    }
+
+    let unpushed =
+        git::exec("rev-list", vec!["--reverse", "--topo-order", "HEAD", "--not", "--remotes"])?;
+    let Some(oldest) = unpushed.lines().next() else {
+        return Ok(None);
+    };
+
+    match rev(&format!("{oldest}^")) {
+        Ok(parent) => Ok(Some(Base::Rev(parent))),
+        Err(_) => Ok(Some(Base::Root)),
+    }
+}
+
+/// Split `<base>..[<end>]` (or `<base>`) into its base and end
+fn split_range(range: &str) -> Result<(&str, &str)> {
+    let (base, end) = range.split_once("..").unwrap_or((range, ""));
+
+    if base.is_empty() || end.starts_with('.') {
+        return fail!("{}", stylize(format!("Invalid range '{range}', use <base>.."), "red"));
+    }
+
+    Ok((base, end))
+}
+
+/// The full id of the commit a revision points to
+fn rev(rev: &str) -> Result<String> {
+    let commit = format!("{rev}^{{commit}}");
+    match git::exec("rev-parse", vec!["--verify", "-q", &commit]) {
+        Ok(sha) => Ok(s!(sha.trim())),
+        Err(_) => fail!("{}", stylize(format!("Unknown revision '{rev}'"), "red")),
+    }
+}
+
+/// The commits after `base`, oldest first
+fn log(base: &Base) -> Result<Vec<Commit>> {
+    let spec = match base {
+        Base::Rev(rev) => format!("{rev}..HEAD"),
+        Base::Root => s!("HEAD"),
+    };
+
+    let output =
+        git::exec("log", vec!["--reverse", "--format=%H%x00%h%x00%an <%ae>%x00%s", &spec])?;
+
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.splitn(4, '\0').collect();
+            match fields.as_slice() {
+                [sha, short, author, subject] => Some(Commit {
+                    sha: s!(*sha),
+                    short: s!(*short),
+                    author: s!(*author),
+                    subject: s!(*subject),
+                }),
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_range;
+
+    #[test]
+    fn test_split_range() {
+        assert_eq!(split_range("HEAD~3").unwrap(), ("HEAD~3", ""));
+        assert_eq!(split_range("origin/main..").unwrap(), ("origin/main", ""));
+        assert_eq!(split_range("abc123..HEAD").unwrap(), ("abc123", "HEAD"));
+        assert!(split_range("..HEAD").is_err());
+        assert!(split_range("main...HEAD").is_err());
+    }
+}
@@ -0,0 +1,195 @@
+//! # usr/guard
+//!
+//! `usr guard install` adds a git hook (`pre-commit` by default, or `commit-msg`) that runs
+//! `usr check` before every commit, so commits made with the wrong identity are refused.
+//!
+//! The hook is installed in the hooks directory git runs for the current repository (its
+//! `core.hooksPath` if the repository sets one), or with `--global` in the global `core.hooksPath`.
+//! The global `core.hooksPath` is never set by `usr`: it disables the hooks of every repository, so
+//! it's up to the user to set it. A hook that was already there is kept as `<hook>.chained` and
+//! run after the check; a global hook also runs the hook of the repository, since git ignores it
+//! once `core.hooksPath` is set.
+
+use {
+    common::git,
+    eyre::Result,
+    lool::{cli::stylize::stylize, fail, s},
+    std::{
+        env, fs,
+        path::{Path, PathBuf},
+    },
+};
+
+pub const HOOKS: [&str; 2] = ["pre-commit", "commit-msg"];
+
+/// Marks the hooks written by `usr guard install`, so installing again replaces them
+const MARKER: &str = "# installed by `gusr guard install`";
+
+pub fn install(hook: &str, global: bool) -> Result<()> {
+    let dir = if global {
+        global_hooks_dir()?
+    } else {
+        repo_hooks_dir()?
+    };
+
+    let path = write_hook(&dir, hook, &env::current_exe()?, global)?;
+
+    println!("{} {}", stylize("Identity guard installed in", "green"), path.display());
+    Ok(())
+}
+
+/// Write the hook in `dir`, keeping the hook that was already there as `<hook>.chained`
+fn write_hook(dir: &Path, hook: &str, exe: &Path, global: bool) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+
+    let path = dir.join(hook);
+    let chained = dir.join(format!("{hook}.chained"));
+
+    if path.exists() && !fs::read_to_string(&path).unwrap_or_default().contains(MARKER) {
+        if chained.exists() {
+            return fail!(
+                "{}\nMove it away and try again",
+                stylize(format!("{} already exists", chained.display()), "red")
+            );
+        }
+
+        fs::rename(&path, &chained)?;
+        println!("{} {}", stylize("Existing hook kept as", "+dim"), chained.display());
+    }
+
+    fs::write(&path, script(hook, exe, global))?;
+    make_executable(&path)?;
+
+    Ok(path)
+}
+
+/// The hook: check the identity, then run the chained hook
+const SCRIPT: &str = r#"#!/bin/sh
+{marker}
+
+"{exe}" check || exit 1
+
+hook_dir=$(dirname "$0")
+if [ -x "$hook_dir/{hook}.chained" ]; then
+    "$hook_dir/{hook}.chained" "$@" || exit $?
+fi
+"#;
+
+/// Appended to global hooks: git skips the hooks of the repository when `core.hooksPath` is set
+const REPO_HOOK: &str = r#"
+repo_hook="$(git rev-parse --git-common-dir)/hooks/{hook}"
+if [ -x "$repo_hook" ]; then
+    "$repo_hook" "$@" || exit $?
+fi
+"#;
+
+fn script(hook: &str, exe: &Path, global: bool) -> String {
+    let mut script = s!(SCRIPT);
+    if global {
+        script.push_str(REPO_HOOK);
+    }
+
+    script
+        .replace("{marker}", MARKER)
+        .replace("{exe}", &exe.to_string_lossy())
+        .replace("{hook}", hook)
+}
+
+/// The hooks directory git runs for the current repository
+fn repo_hooks_dir() -> Result<PathBuf> {
+    if git::exec("rev-parse", vec!["--git-dir"]).is_err() {
+        return fail!("{}", stylize("Not in a git repository", "red"));
+    }
+
+    // a `core.hooksPath` set outside the repository is shared with other repositories
+    if let Ok(hooks_path) = git::exec("config", vec!["core.hooksPath"]) {
+        if git::exec("config", vec!["--local", "core.hooksPath"]).is_err() {
+            return fail!(
+                "{} {}\nUse {} to install the guard there",
+                stylize("core.hooksPath is set outside the repository:", "red"),
+                hooks_path.trim(),
+                stylize("--global", "yellow")
+            );
+        }
+    }
+
+    let dir = git::exec("rev-parse", vec!["--path-format=absolute", "--git-path", "hooks"])?;
+    Ok(PathBuf::from(dir.trim()))
+}
+
+/// The global `core.hooksPath`, failing if it isn't set
+fn global_hooks_dir() -> Result<PathBuf> {
+    match git::exec("config", vec!["--global", "--type=path", "core.hooksPath"]) {
+        Ok(dir) => Ok(PathBuf::from(dir.trim())),
+        Err(_) => fail!(
+            "{}\nSetting it disables the hooks of every repository, set it yourself with {} and \
+             try again",
+            stylize("The global core.hooksPath isn't set", "red"),
+            stylize("git config --global core.hooksPath <dir>", "yellow")
+        ),
+    }
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{script, write_hook, MARKER},
+        std::{env, fs, path::Path},
+    };
+
+    #[test]
+    fn test_script() {
+        let exe = Path::new("/opt/gitools/gusr");
+
+        let local = script("commit-msg", exe, false);
+        assert!(local.contains(MARKER));
+        assert!(local.contains("\"/opt/gitools/gusr\" check || exit 1"));
+        assert!(local.contains("$hook_dir/commit-msg.chained"));
+        assert!(!local.contains("repo_hook"));
+
+        let global = script("commit-msg", exe, true);
+        assert!(global.contains("/hooks/commit-msg\""));
+        assert!(!global.contains('{'));
+    }
+
+    #[test]
+    fn test_write_hook_chains_existing() {
+        let dir = env::temp_dir().join(format!("gusr-guard-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let exe = Path::new("/opt/gitools/gusr");
+        fs::write(dir.join("pre-commit"), "#!/bin/sh\nlint\n").unwrap();
+
+        let path = write_hook(&dir, "pre-commit", exe, false).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains(MARKER));
+        assert_eq!(
+            fs::read_to_string(dir.join("pre-commit.chained")).unwrap(),
+            "#!/bin/sh\nlint\n"
+        );
+
+        // installing again replaces the guard and keeps the chained hook
+        write_hook(&dir, "pre-commit", exe, false).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("pre-commit.chained")).unwrap(),
+            "#!/bin/sh\nlint\n"
+        );
+
+        // a new hook can't be chained when there is one already
+        fs::write(dir.join("pre-commit"), "#!/bin/sh\nformat\n").unwrap();
+        assert!(write_hook(&dir, "pre-commit", exe, false).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+//! # usr/history
+//!
+//! Every change to `gusr.toml` keeps a copy of the previous version in a `history` directory next
+//! to it: `gusr.toml.1` is the most recent one and only the last [`SIZE`] versions are kept.
+//! `gusr undo` restores the most recent backup and drops it from the history, so undoing again
+//! goes further back.
+
+use {
+    eyre::Result,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// How many previous versions are kept
+const SIZE: usize = 10;
+
+/// The path of the `n`th most recent backup of `file`
+fn backup(file: &Path, n: usize) -> PathBuf {
+    let name = file.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let mut path = file.to_path_buf();
+    path.pop();
+    path.push("history");
+    path.push(format!("{name}.{n}"));
+    path
+}
+
+/// Keep a copy of the current version of `file`, shifting the older ones and dropping the oldest
+pub fn push(file: &Path) -> Result<()> {
+    if !file.exists() {
+        return Ok(());
+    }
+
+    let newest = backup(file, 1);
+    if let Some(dir) = newest.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let _ = fs::remove_file(backup(file, SIZE));
+    for n in (1..SIZE).rev() {
+        if backup(file, n).exists() {
+            fs::rename(backup(file, n), backup(file, n + 1))?;
+        }
+    }
+
+    fs::copy(file, newest)?;
+    Ok(())
+}
+
+/// The content of the most recent backup of `file`, if any
+pub fn last(file: &Path) -> Option<String> {
+    fs::read_to_string(backup(file, 1)).ok()
+}
+
+/// Drop the most recent backup of `file`, shifting the older ones
+pub fn pop(file: &Path) -> Result<()> {
+    fs::remove_file(backup(file, 1))?;
+
+    for n in 2..=SIZE {
+        if backup(file, n).exists() {
+            fs::rename(backup(file, n), backup(file, n - 1))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{last, pop, push, SIZE},
+        std::fs,
+    };
+
+    #[test]
+    fn test_rotation() {
+        let dir = std::env::temp_dir().join(format!("gusr-history-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("gusr.toml");
+
+        for version in 0..=SIZE + 2 {
+            push(&file).unwrap();
+            fs::write(&file, version.to_string()).unwrap();
+        }
+
+        // only the versions before the current one are kept, the oldest ones were dropped
+        for version in (2..=SIZE + 1).rev() {
+            assert_eq!(last(&file), Some(version.to_string()));
+            pop(&file).unwrap();
+        }
+        assert_eq!(last(&file), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
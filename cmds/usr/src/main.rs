@@ -2,23 +2,77 @@
 //!
 //! This command is a git utility to manage git user settings as profiles.
 //!
-//! It stores "user profiles" (name, email, signingkey) in a TOML file called `gusr.toml` in the
-//! same directory as the executable.
+//! It stores "user profiles" (name, email and signing settings) in a TOML file called `gusr.toml`
+//! in the gitools config directory: `$GITOOLS_CONFIG`, `$XDG_CONFIG_HOME/gitools` or
+//! `~/.config/gitools`. A `gusr.toml` left next to the executable by older versions is moved
+//! there the first time it's read, along with the `profiles` directory of `usr bind` (updating the
+//! bindings of the global config). The file is validated when loaded: ids must be unique and
+//! emails well formed.
 //!
 //! The user can add, list, and delete profiles to the `gusr.toml` file:
 //!
 //! - `usr`: show the current user profile
 //! - `usr list`: list all user profiles
 //! - `usr add`: add a new user profile
+//! - `usr edit [<id>]`: edit a profile in a form prefilled with its current fields, or change
+//!   some of them with `usr edit <id> [--id ..] [--name ..] [--email ..] [--key ..]`
 //! - `usr rm`: remove a user profile
+//! - `usr undo`: restore the profiles as they were before the last change. The previous versions
+//!   of `gusr.toml` are kept in a `history` directory next to it (the last 10 of them).
+//!
+//! Profiles can be shared between machines or with a team through bundles, in the same format as
+//! `gusr.toml` (TOML, or JSON with `--json` or a `.json` file):
+//!
+//! - `usr export [--ids <id>,...] [--json] [--no-keys] [-o <file>]`: write the given profiles (all
+//!   of them by default) to a bundle, optionally without their signing keys and settings.
+//! - `usr import <file> [--replace | --skip]`: add the profiles of a bundle, asking what to do with
+//!   the ones whose id is already used (replace it, skip it or import it with another id).
+//!
+//! Every command can be used without prompts, for scripts: `usr add --id <id> --name <name>
+//! --email <email> [--key <key>]`, `usr set <id>` and `usr rm <id> --yes`. `usr --json` and
+//! `usr list --json` print the profiles as JSON.
 //!
 //! And most importantly, the user can switch between profiles:
 //!
 //! - `usr set`: display a selectable list of profiles to switch to. Uses `git config --global` to
-//!   set the `user.name`, `user.email`, and `user.signingkey` to match the selected profile.
+//!   set the `user.name`, `user.email`, and `user.signingkey` to match the selected profile, along
+//!   with the signing settings (`gpg.format`, `commit.gpgsign`, `tag.gpgsign`, `gpg.program` and
+//!   `gpg.ssh.allowedSignersFile`), unsetting the ones the profile doesn't have. The ssh key and
+//!   allowed signers files are checked to exist first.
+//!
+//!   Any other git config key can be set per profile in its `[profile.config]` table (e.g.
+//!   `core.sshCommand` or `http.proxy`). The keys set by the previous profile are removed when
+//!   switching, so none of them is left behind, and the values they replaced are restored.
 //!
 //!   It also sets a custom git configuration `gusrs.active` to the selected profile ID so that it
 //!   can then be used to know which profile is currently active.
+//!
+//! - `usr set --local`: same, but using `git config --local`, so the profile only applies to the
+//!   current repository.
+//!
+//! - `usr bind <profile> <dir>`: write the profile to its own git config file
+//!   (`profiles/<id>.gitconfig` next to `gusr.toml`) and add an `includeIf "gitdir:<dir>/"` entry
+//!   pointing to it to the global config, so the repositories under `<dir>` use the profile
+//!   without switching.
+//!
+//! Profiles can have match rules (`match = ["github.com/acme/*", "~/work/**"]`), globs of the
+//! remotes (`<host>/<owner>/<repo>`) or paths of the repositories that should use them. In a
+//! repository, `usr` warns if the active profile isn't the one matching it, and `usr auto` applies
+//! the matching profile to the repository (like `usr set --local`).
+//!
+//! - `usr check`: fail if git would commit with an email other than the one of the profile
+//!   matching the repository.
+//!
+//! - `usr guard install [--global] [--hook pre-commit|commit-msg]`: install a hook running
+//!   `usr check` before each commit, in the hooks directory of the current repository or in the
+//!   global `core.hooksPath` (which must already be set). Existing hooks are kept and run after the
+//!   check.
+//!
+//! - `usr fix [<range>] [--profile <id>] [--force] [--yes]`: rewrite the author and committer of
+//!   the unpushed commits of the current branch (or the ones after `<base>` in `<base>..`) to a
+//!   profile (the given one, the one matching the repository or the chosen one), signing them again
+//!   if the profile has a key. The commits are shown before asking for confirmation, and commits
+//!   already on a remote-tracking branch are only rewritten with `--force`.
 
 use {
     args::{parse_args, Action},
@@ -28,7 +82,13 @@ use {
 
 mod action;
 mod args;
+mod bundle;
+mod fix;
+mod guard;
+mod history;
 mod profiles;
+mod rules;
+mod signing;
 
 const COMMAND: &str = "gusr";
 
@@ -36,11 +96,34 @@ fn main() -> Result<()> {
     match parse_args()? {
         Action::Version => print::version(COMMAND, env!("CARGO_PKG_VERSION")),
         Action::Help => print::tldr(COMMAND),
-        Action::Add => action::add(),
-        Action::List => action::list(),
+        Action::Add(args) => action::add(args),
+        Action::Edit { id, changes } => action::edit(id, changes),
+        Action::List { json } => action::list(json),
         Action::ShowConfig => action::show_config(),
-        Action::Remove => action::remove(),
-        Action::Set => action::set(),
-        Action::View => action::view(),
+        Action::Remove { id, yes } => action::remove(id, yes),
+        Action::Set { id, local } => action::set(id, local),
+        Action::Bind { id, dir } => action::bind(id, dir),
+        Action::Auto => action::auto(),
+        Action::Check => action::check(),
+        Action::Guard { hook, global } => guard::install(&hook, global),
+        Action::Undo => action::undo(),
+        Action::Export {
+            ids,
+            json,
+            keys,
+            output,
+        } => action::export(ids, json, keys, output),
+        Action::Import {
+            file,
+            replace,
+            skip,
+        } => action::import(file, replace, skip),
+        Action::Fix {
+            range,
+            id,
+            force,
+            yes,
+        } => action::fix(range, id, force, yes),
+        Action::View { json } => action::view(json),
     }
 }
@@ -1,15 +1,72 @@
 use {
+    crate::{history, signing::Format},
+    common::{config, git},
+    eyre::Result,
+    lool::{cli::stylize::stylize, fail},
     serde::{Deserialize, Serialize},
-    std::{borrow::Cow, fs, path::PathBuf, process},
+    std::{
+        borrow::Cow,
+        collections::{BTreeMap, HashSet},
+        fs,
+        path::{Path, PathBuf},
+    },
     tabled::Tabled,
 };
 
-#[derive(Deserialize, Serialize, Debug)]
+const FILE_NAME: &str = "gusr.toml";
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Profile {
     pub id: String,
     pub name: String,
     pub email: String,
+    /// gpg key id, or the path of the ssh key (or the literal key) with the ssh format
     pub signingkey: Option<String>,
+    /// `gpg.format`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<Format>,
+    /// `commit.gpgsign`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpgsign: Option<bool>,
+    /// `tag.gpgsign`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tagsign: Option<bool>,
+    /// `gpg.program`, or `gpg.<format>.program` for the ssh and x509 formats
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program: Option<String>,
+    /// `gpg.ssh.allowedSignersFile`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowedsigners: Option<String>,
+    /// globs of the remotes or paths of the repositories that should use the profile (see
+    /// [`crate::rules`])
+    #[serde(default, rename = "match", skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<String>,
+    /// extra git config keys (e.g. `core.sshCommand`), from the `[profile.config]` table
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub config: BTreeMap<String, ConfigValue>,
+}
+
+/// The value of an extra git config key; a list sets a multi-valued key (e.g.
+/// `url.<base>.insteadOf`)
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ConfigValue {
+    Bool(bool),
+    Int(i64),
+    String(String),
+    List(Vec<String>),
+}
+
+impl ConfigValue {
+    /// The values to set for the key
+    pub fn values(&self) -> Vec<String> {
+        match self {
+            ConfigValue::Bool(value) => vec![value.to_string()],
+            ConfigValue::Int(value) => vec![value.to_string()],
+            ConfigValue::String(value) => vec![value.clone()],
+            ConfigValue::List(values) => values.clone(),
+        }
+    }
 }
 
 impl Tabled for Profile {
@@ -20,12 +77,16 @@ impl Tabled for Profile {
             Cow::from(self.id.as_str()),
             Cow::from(self.name.as_str()),
             Cow::from(self.email.as_str()),
-            Cow::from(self.signingkey.as_deref().unwrap_or("None")),
+            match (&self.signingkey, self.format) {
+                (Some(key), Some(format)) => Cow::from(format!("{key} ({format})")),
+                (Some(key), None) => Cow::from(key.as_str()),
+                (None, _) => Cow::from("None"),
+            },
         ]
     }
 
     fn headers() -> Vec<Cow<'static, str>> {
-        vec![Cow::from("id"), Cow::from("name"), Cow::from("email"), Cow::from("gpg")]
+        vec![Cow::from("id"), Cow::from("name"), Cow::from("email"), Cow::from("key")]
     }
 }
 
@@ -37,58 +98,350 @@ pub struct Config {
 }
 
 impl Config {
-    pub fn load() -> Config {
-        let path = get_profiles_path();
+    /// Read the profiles file (see [`get_profiles_path`]), creating it if it doesn't exist and
+    /// failing if it can't be parsed or isn't valid
+    pub fn load() -> Result<Config> {
+        let path = get_profiles_path()?;
+        migrate(&path)?;
+
         if !path.exists() {
             let profiles = Config {
-                path: Some(String::from(path.to_str().unwrap())),
+                path: Some(path.to_string_lossy().to_string()),
                 profile: Vec::new(),
             };
-            write_profiles(&profiles);
-            return profiles;
+            profiles.save()?;
+            return Ok(profiles);
         }
 
-        let content = fs::read_to_string(&path).unwrap_or_else(|_| {
-            eprintln!("Could not read the profiles file.");
-            process::exit(1);
-        });
+        let Ok(content) = fs::read_to_string(&path) else {
+            return fail!("{} {}", stylize("Could not read", "red"), path.display());
+        };
 
-        let mut cfg: Config = toml::from_str(&content).unwrap_or_else(|_| {
-            eprintln!("Could not parse the profiles file.");
-            process::exit(1);
-        });
+        let mut cfg: Config = match toml::from_str(&content) {
+            Ok(cfg) => cfg,
+            // the error points to the line and column
+            Err(err) => {
+                return fail!("{} {}\n{}", stylize("Could not parse", "red"), path.display(), err)
+            }
+        };
 
-        cfg.path = Some(String::from(path.to_str().unwrap()));
-        cfg
+        if let Err(reason) = cfg.validate() {
+            return fail!("{} {}: {}", stylize("Invalid", "red"), path.display(), reason);
+        }
+
+        cfg.path = Some(path.to_string_lossy().to_string());
+        Ok(cfg)
     }
 
-    pub fn save(&self) {
-        write_profiles(self);
+    pub fn save(&self) -> Result<()> {
+        if let Err(reason) = self.validate() {
+            return fail!("{}: {}", stylize("Invalid profiles", "red"), reason);
+        }
+
+        write_profiles(self)
+    }
+
+    /// Check that the ids are unique and the emails are well formed
+    pub fn validate(&self) -> Result<(), String> {
+        let mut ids = HashSet::new();
+
+        for profile in &self.profile {
+            if profile.id.trim().is_empty() {
+                return Err(format!("the profile of <{}> has an empty id", profile.email));
+            }
+
+            if !ids.insert(&profile.id) {
+                return Err(format!("the id '{}' is used by more than one profile", profile.id));
+            }
+
+            if let Err(reason) = check_email(&profile.email) {
+                return Err(format!("profile '{}': {}", profile.id, reason));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The git config file that `gusr bind` writes a profile to, in a `profiles` directory next to
+    /// the `gusr.toml` file
+    pub fn include_path(&self, id: &str) -> PathBuf {
+        let mut path = self.path.as_ref().map(PathBuf::from).unwrap_or_default();
+        path.pop();
+        path.push("profiles");
+        path.push(format!("{id}.gitconfig"));
+        path
     }
 }
 
-/// Read the profiles from the `gusr.toml` file, located in the same directory as the executable.
-/// If the file does not exist, it will create it with an empty list of profiles.
+/// Check that an email looks like `local@domain`
+pub fn check_email(email: &str) -> Result<(), String> {
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.is_empty()
+                && !domain.contains('@')
+                && !email.contains(|c: char| c.is_whitespace() || c == '<' || c == '>')
+        }
+        None => false,
+    };
 
-fn get_profiles_path() -> PathBuf {
-    let mut path = std::env::current_exe().unwrap_or_else(|_| {
-        eprintln!("Could not get the current executable path.");
-        process::exit(1);
-    });
+    match valid {
+        true => Ok(()),
+        false => Err(format!("'{email}' is not a valid email")),
+    }
+}
+
+/// The path of the `gusr.toml` file, in the gitools config directory (`$GITOOLS_CONFIG`,
+/// `$XDG_CONFIG_HOME/gitools` or `~/.config/gitools`), or next to the executable if there is none.
+fn get_profiles_path() -> Result<PathBuf> {
+    match config::dir() {
+        Some(dir) => Ok(dir.join(FILE_NAME)),
+        None => legacy_path(),
+    }
+}
+
+/// Where `gusr.toml` used to be: in the same directory as the executable
+fn legacy_path() -> Result<PathBuf> {
+    let mut path = std::env::current_exe()?;
     path.pop();
-    path.push("gusr.toml");
-    path
+    path.push(FILE_NAME);
+    Ok(path)
+}
+
+/// Move the profiles from the legacy location to `path`, if they are there and `path` doesn't
+/// exist yet, along with the include files of the profiles bound to directories
+fn migrate(path: &Path) -> Result<()> {
+    let legacy = legacy_path()?;
+
+    if !path.exists() && legacy.exists() && legacy != path {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&legacy, path)?;
+        // the executable's directory may not be writable, in which case the old file is left there
+        let _ = fs::remove_file(&legacy);
+
+        eprintln!(
+            "{} {} {} {}",
+            stylize("Moved the profiles from", "+dim"),
+            legacy.display(),
+            stylize("to", "+dim"),
+            path.display()
+        );
+    }
+
+    // checked on its own, since older versions moved `gusr.toml` but not the include files
+    migrate_includes(&legacy, path, "--global")
+}
+
+/// Move the include files written by `usr bind` from the `profiles` directory next to `legacy` to
+/// the one next to `path`, pointing the `includeIf` entries of the `config` file (`--global` or
+/// `--file=<file>`) that used them to their new location
+fn migrate_includes(legacy: &Path, path: &Path, config: &str) -> Result<()> {
+    let (Some(from), Some(to)) = (legacy.parent(), path.parent()) else {
+        return Ok(());
+    };
+    let (from, to) = (from.join("profiles"), to.join("profiles"));
+
+    if from == to || !from.is_dir() {
+        return Ok(());
+    }
+
+    let mut moved = 0;
+    fs::create_dir_all(&to)?;
+    for entry in fs::read_dir(&from)? {
+        let file = entry?.path();
+        let Some(name) = file.file_name().filter(|_| file.is_file()) else {
+            continue;
+        };
+
+        let dest = to.join(name);
+        if !dest.exists() {
+            fs::copy(&file, &dest)?;
+            moved += 1;
+        }
+        // the executable's directory may not be writable, in which case the old files are left
+        let _ = fs::remove_file(&file);
+    }
+    let _ = fs::remove_dir(&from);
+
+    let bindings =
+        git::exec("config", vec![config, "--null", "--get-regexp", r"^includeif\..*\.path$"])
+            .unwrap_or_default();
+
+    for binding in bindings.split('\0') {
+        let Some((key, include)) = binding.split_once('\n') else {
+            continue;
+        };
+        let Ok(file) = Path::new(include).strip_prefix(&from) else {
+            continue;
+        };
+
+        let moved = to.join(file).to_string_lossy().to_string();
+        git::exec("config", vec![config, "--fixed-value", "--replace-all", key, &moved, include])?;
+    }
+
+    if moved > 0 {
+        eprintln!(
+            "{} {} {} {}",
+            stylize("Moved the profiles bound to directories from", "+dim"),
+            from.display(),
+            stylize("to", "+dim"),
+            to.display()
+        );
+    }
+    Ok(())
 }
 
-fn write_profiles(profiles: &Config) {
-    let path = get_profiles_path();
-    let content = toml::to_string_pretty(profiles).unwrap_or_else(|_| {
-        eprintln!("Could not serialize the profiles.");
-        process::exit(1);
-    });
+/// Restore the version of the profiles before the last change, returning it (or `None` if there
+/// is no history left)
+pub fn undo() -> Result<Option<Config>> {
+    let path = get_profiles_path()?;
+
+    let Some(content) = history::last(&path) else {
+        return Ok(None);
+    };
+
+    let mut cfg: Config = match toml::from_str(&content) {
+        Ok(cfg) => cfg,
+        Err(err) => return fail!("{}\n{}", stylize("Could not parse the backup", "red"), err),
+    };
+
+    if let Err(reason) = cfg.validate() {
+        return fail!("{}: {}", stylize("Invalid backup", "red"), reason);
+    }
+
+    write_atomic(&path, &content)?;
+    history::pop(&path)?;
+
+    cfg.path = Some(path.to_string_lossy().to_string());
+    Ok(Some(cfg))
+}
 
-    fs::write(path, content).unwrap_or_else(|_| {
-        eprintln!("Could not write the profiles file.");
-        process::exit(1);
-    });
+/// Write the profiles, keeping the previous version in the history if they changed
+fn write_profiles(profiles: &Config) -> Result<()> {
+    let path = get_profiles_path()?;
+    let content = toml::to_string_pretty(profiles)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if fs::read_to_string(&path).is_ok_and(|current| current == content) {
+        return Ok(());
+    }
+
+    history::push(&path)?;
+    write_atomic(&path, &content)
+}
+
+/// Write to a temporary file next to `path` and rename it, so an interrupted write never leaves a
+/// truncated file behind
+fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    let tmp = PathBuf::from(tmp);
+
+    if fs::write(&tmp, content).and_then(|_| fs::rename(&tmp, path)).is_err() {
+        let _ = fs::remove_file(&tmp);
+        return fail!("{} {}", stylize("Could not write", "red"), path.display());
+    }
+
+    Ok(())
+}
+
+/// Replace a leading `~/` with the home directory
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{rest}", home.trim_end_matches('/')),
+        _ => String::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{check_email, migrate_includes, Config, ConfigValue},
+        common::git,
+        std::{env, fs},
+    };
+
+    #[test]
+    fn test_check_email() {
+        for email in ["john@acme.com", "j.doe+git@localhost"] {
+            assert!(check_email(email).is_ok(), "{email}");
+        }
+        for email in ["", "john", "@acme.com", "john@", "john doe@acme.com", "a@b@c", "<j@a.com>"] {
+            assert!(check_email(email).is_err(), "{email}");
+        }
+    }
+
+    #[test]
+    fn test_validate_duplicate_ids() {
+        let config: Config = toml::from_str(
+            r#"
+            [[profile]]
+            id = "work"
+            name = "Worker"
+            email = "w@corp"
+
+            [[profile]]
+            id = "work"
+            name = "Me"
+            email = "me@home"
+            "#,
+        )
+        .unwrap();
+
+        assert!(config.validate().unwrap_err().contains("'work'"));
+    }
+
+    #[test]
+    fn test_profile_config_table() {
+        let config: Config = toml::from_str(
+            r#"
+            [[profile]]
+            id = "work"
+            name = "Worker"
+            email = "w@corp"
+
+            [profile.config]
+            "core.sshCommand" = "ssh -i ~/.ssh/work"
+            "http.sslVerify" = false
+            "url.git@github.com:.insteadOf" = ["https://github.com/", "gh:"]
+            "#,
+        )
+        .unwrap();
+
+        let extra = &config.profile[0].config;
+        assert_eq!(extra["core.sshCommand"].values(), vec!["ssh -i ~/.ssh/work"]);
+        assert_eq!(extra["http.sslVerify"], ConfigValue::Bool(false));
+        assert_eq!(extra["url.git@github.com:.insteadOf"].values().len(), 2);
+    }
+
+    #[test]
+    fn test_migrate_includes() {
+        let dir = env::temp_dir().join(format!("gusr-migrate-{}", std::process::id()));
+        let (legacy, path) = (dir.join("bin/gusr.toml"), dir.join("config/gusr.toml"));
+        fs::create_dir_all(dir.join("bin/profiles")).unwrap();
+        fs::write(dir.join("bin/profiles/work.gitconfig"), "[user]\n\temail = w@corp\n").unwrap();
+
+        let global = dir.join("global").to_string_lossy().to_string();
+        let config = format!("--file={global}");
+        let include = dir.join("bin/profiles/work.gitconfig").to_string_lossy().to_string();
+        let key = "includeIf.gitdir:/src/work/.path";
+        git::exec("config", vec![&config, key, &include]).unwrap();
+        git::exec("config", vec![&config, "include.path", "/etc/other.gitconfig"]).unwrap();
+
+        migrate_includes(&legacy, &path, &config).unwrap();
+
+        let moved = dir.join("config/profiles/work.gitconfig");
+        assert!(moved.is_file());
+        assert!(!dir.join("bin/profiles").exists());
+        let get = |key| git::exec("config", vec![&config, "--get", key]).unwrap();
+        assert_eq!(get(key).trim(), moved.to_string_lossy());
+        assert_eq!(get("include.path").trim(), "/etc/other.gitconfig");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,100 @@
+//! # usr/rules
+//!
+//! Match rules of the profiles (`match = [...]` in `gusr.toml`), used to find the profile a
+//! repository should use. A rule is a glob pattern matched against:
+//!
+//! - the remotes of the repository, as `<host>/<owner>/<repo>` (e.g. `github.com/acme/*`), no
+//!   matter if the url is https, ssh or scp-like (`git@github.com:acme/repo.git`)
+//! - the path of the repository, if the rule starts with `/` or `~/` (e.g. `~/work/**`)
+
+use {crate::profiles::expand_home, glob::Pattern, lool::s, std::path::Path};
+
+/// What a repository is matched by: its remotes (name, url) and its root directory
+pub struct Repo<'a> {
+    pub remotes: &'a [(String, String)],
+    pub path: &'a Path,
+}
+
+/// Find the first of the rules that matches the repository, along with what it matched (the
+/// remote name or the path)
+pub fn find_match<'a>(rules: &'a [String], repo: &Repo) -> Option<(&'a str, String)> {
+    rules.iter().find_map(|rule| {
+        if is_path_rule(rule) {
+            let pattern = Pattern::new(&expand_home(rule)).ok()?;
+            let path = repo.path.to_string_lossy();
+
+            return pattern.matches(&path).then(|| (rule.as_str(), s!(path)));
+        }
+
+        let pattern = Pattern::new(rule.trim_end_matches(".git")).ok()?;
+        repo.remotes
+            .iter()
+            .find(|(_, url)| pattern.matches(&normalize_url(url)))
+            .map(|(name, _)| (rule.as_str(), format!("remote {name}")))
+    })
+}
+
+fn is_path_rule(rule: &str) -> bool {
+    rule.starts_with('/') || rule.starts_with("~/")
+}
+
+/// Turn a remote url into `<host>/<path>`, without the scheme, user, port and `.git` suffix
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/').trim_end_matches(".git");
+
+    let (host, path) = match url.split_once("://") {
+        // scheme://[user@]host[:port]/path
+        Some((_, rest)) => {
+            let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+            let host = authority.rsplit('@').next().unwrap_or(authority);
+            (host.split(':').next().unwrap_or(host), path)
+        }
+        // [user@]host:path
+        None => match url.split_once(':') {
+            Some((authority, path)) => (authority.rsplit('@').next().unwrap_or(authority), path),
+            // a local path
+            None => return s!(url),
+        },
+    };
+
+    format!("{}/{}", host.to_lowercase(), path.trim_start_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{find_match, normalize_url, Repo},
+        lool::s,
+        std::path::Path,
+    };
+
+    #[test]
+    fn test_normalize_url() {
+        for url in [
+            "git@github.com:acme/api.git",
+            "https://github.com/acme/api.git",
+            "https://user@GitHub.com/acme/api",
+            "ssh://git@github.com:22/acme/api.git",
+        ] {
+            assert_eq!(normalize_url(url), "github.com/acme/api", "{url}");
+        }
+    }
+
+    #[test]
+    fn test_find_match() {
+        let remotes = [(s!("origin"), s!("git@github.com:acme/api.git"))];
+        let repo = Repo {
+            remotes: &remotes,
+            path: Path::new("/src/work/api"),
+        };
+
+        let rules = [s!("gitlab.com/*"), s!("github.com/acme/*")];
+        assert_eq!(find_match(&rules, &repo), Some(("github.com/acme/*", s!("remote origin"))));
+
+        let rules = [s!("/src/work/**")];
+        assert_eq!(find_match(&rules, &repo), Some(("/src/work/**", s!("/src/work/api"))));
+
+        let rules = [s!("github.com/other/*"), s!("/src/personal/*")];
+        assert_eq!(find_match(&rules, &repo), None);
+    }
+}
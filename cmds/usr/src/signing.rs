@@ -0,0 +1,171 @@
+//! # usr/signing
+//!
+//! Commit and tag signing settings of a profile:
+//!
+//! ```toml
+//! [[profile]]
+//! id = "work"
+//! # ...
+//! signingkey = "~/.ssh/id_ed25519.pub"  # gpg key id, ssh key path or literal ssh key
+//! format = "ssh"                        # gpg.format: openpgp (default), ssh or x509
+//! gpgsign = true                        # commit.gpgsign
+//! tagsign = true                        # tag.gpgsign
+//! program = "/usr/bin/ssh-keygen"       # gpg.program (gpg.ssh.program, gpg.x509.program)
+//! allowedsigners = "~/.ssh/allowed_signers"  # gpg.ssh.allowedSignersFile
+//! ```
+//!
+//! All of them are written when a profile is applied, and the ones the profile doesn't have are
+//! unset, so nothing is left behind from the previous profile.
+
+use {
+    crate::profiles::{expand_home, Profile},
+    lool::s,
+    serde::{Deserialize, Serialize},
+    std::{
+        fmt::{self, Display},
+        path::Path,
+    },
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Openpgp,
+    Ssh,
+    X509,
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Openpgp => write!(f, "openpgp"),
+            Format::Ssh => write!(f, "ssh"),
+            Format::X509 => write!(f, "x509"),
+        }
+    }
+}
+
+/// The git settings for signing, with `None` for the ones to unset. In a scope that `inherits` the
+/// global config (a repository or an included file), unsetting the signing flags wouldn't override
+/// the global ones, so they are written as `false` instead.
+pub fn git_config(profile: &Profile, inherits: bool) -> Vec<(&'static str, Option<String>)> {
+    let format = profile.format.unwrap_or_default();
+    let program = |of: Format| profile.program.clone().filter(|_| format == of);
+    let flag =
+        |sign: Option<bool>| sign.or(Some(false).filter(|_| inherits)).map(|sign| sign.to_string());
+
+    vec![
+        (
+            "user.signingkey",
+            profile.signingkey.as_deref().map(|key| signing_key(format, key)),
+        ),
+        ("gpg.format", profile.format.map(|format| format.to_string())),
+        ("commit.gpgsign", flag(profile.gpgsign)),
+        ("tag.gpgsign", flag(profile.tagsign)),
+        ("gpg.program", program(Format::Openpgp)),
+        ("gpg.ssh.program", program(Format::Ssh)),
+        ("gpg.x509.program", program(Format::X509)),
+        ("gpg.ssh.allowedSignersFile", profile.allowedsigners.as_deref().map(expand_home)),
+    ]
+}
+
+/// Check that the files the signing settings point to exist
+pub fn validate(profile: &Profile) -> Result<(), String> {
+    if let Some(key) = &profile.signingkey {
+        if profile.format == Some(Format::Ssh) && !is_literal_ssh_key(key) {
+            let path = expand_home(key);
+            if !Path::new(&path).is_file() {
+                return Err(format!("the ssh key file '{path}' doesn't exist"));
+            }
+        }
+    }
+
+    if let Some(file) = &profile.allowedsigners {
+        let path = expand_home(file);
+        if !Path::new(&path).is_file() {
+            return Err(format!("the allowed signers file '{path}' doesn't exist"));
+        }
+    }
+
+    Ok(())
+}
+
+/// The `user.signingkey` value: ssh key paths are made absolute and literal ssh keys get the
+/// `key::` prefix git expects
+fn signing_key(format: Format, key: &str) -> String {
+    match format {
+        Format::Ssh if key.starts_with("key::") => s!(key),
+        Format::Ssh if is_literal_ssh_key(key) => format!("key::{key}"),
+        Format::Ssh => expand_home(key),
+        _ => s!(key),
+    }
+}
+
+fn is_literal_ssh_key(key: &str) -> bool {
+    key.starts_with("key::")
+        || key.starts_with("ssh-")
+        || key.starts_with("ecdsa-")
+        || key.starts_with("sk-")
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{git_config, validate, Format},
+        crate::profiles::Profile,
+        lool::s,
+    };
+
+    fn profile(format: Option<Format>, key: Option<&str>) -> Profile {
+        Profile {
+            id: s!("work"),
+            name: s!("Worker"),
+            email: s!("w@corp"),
+            signingkey: key.map(String::from),
+            format,
+            gpgsign: Some(true),
+            tagsign: None,
+            program: Some(s!("/usr/bin/ssh-keygen")),
+            allowedsigners: None,
+            rules: vec![],
+            config: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_git_config() {
+        let config = git_config(&profile(Some(Format::Ssh), Some("ssh-ed25519 AAAA")), false);
+        let get = |key| config.iter().find(|(k, _)| *k == key).unwrap().1.clone();
+
+        assert_eq!(get("user.signingkey"), Some(s!("key::ssh-ed25519 AAAA")));
+        assert_eq!(get("gpg.format"), Some(s!("ssh")));
+        assert_eq!(get("commit.gpgsign"), Some(s!("true")));
+        assert_eq!(get("tag.gpgsign"), None);
+        assert_eq!(get("gpg.program"), None);
+        assert_eq!(get("gpg.ssh.program"), Some(s!("/usr/bin/ssh-keygen")));
+    }
+
+    #[test]
+    fn test_git_config_inherited_flags() {
+        let mut unsigned = profile(None, None);
+        unsigned.gpgsign = None;
+        let get = |config: &[(&str, Option<String>)], key| {
+            config.iter().find(|(k, _)| *k == key).unwrap().1.clone()
+        };
+
+        let global = git_config(&unsigned, false);
+        assert_eq!(get(&global, "commit.gpgsign"), None);
+
+        let local = git_config(&unsigned, true);
+        assert_eq!(get(&local, "commit.gpgsign"), Some(s!("false")));
+        assert_eq!(get(&local, "tag.gpgsign"), Some(s!("false")));
+    }
+
+    #[test]
+    fn test_validate_key_file() {
+        assert!(validate(&profile(Some(Format::Ssh), Some("ssh-ed25519 AAAA"))).is_ok());
+        assert!(validate(&profile(None, Some("Q1W2E3R4T5Y6U7I8"))).is_ok());
+        assert!(validate(&profile(Some(Format::Ssh), Some("/nonexistent/id.pub"))).is_err());
+    }
+}
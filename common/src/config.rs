@@ -0,0 +1,87 @@
+//! # common/config
+//!
+//! The gitools configuration file, `config.toml`, shared by all the commands. It lives in the
+//! gitools config directory, which is the first of:
+//!
+//! - `$GITOOLS_CONFIG`
+//! - `$XDG_CONFIG_HOME/gitools`
+//! - `~/.config/gitools`
+//!
+//! ```toml
+//! [branches]
+//! protected = ["main", "release/*"]
+//! ```
+//!
+//! A missing file is the same as an empty one.
+
+use {
+    eyre::Result,
+    lool::{cli::stylize::stylize, fail},
+    serde::Deserialize,
+    std::{env, fs, path::PathBuf},
+};
+
+const FILE_NAME: &str = "config.toml";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Config {
+    pub branches: Branches,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Branches {
+    /// glob patterns of the protected branches
+    pub protected: Vec<String>,
+}
+
+impl Config {
+    /// Read the configuration file, failing if it can't be read or parsed
+    pub fn load() -> Result<Self> {
+        let Some(path) = dir().map(|dir| dir.join(FILE_NAME)) else {
+            return Ok(Config::default());
+        };
+
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let content = fs::read_to_string(&path)?;
+
+        match toml::from_str(&content) {
+            Ok(config) => Ok(config),
+            Err(err) => fail!(
+                "{} {}\n{}",
+                stylize("Invalid config file", "red"),
+                stylize(path.to_string_lossy(), "yellow"),
+                err
+            ),
+        }
+    }
+}
+
+/// The gitools config directory, if it can be determined
+pub fn dir() -> Option<PathBuf> {
+    let var = |name| env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+
+    var("GITOOLS_CONFIG")
+        .or_else(|| var("XDG_CONFIG_HOME").map(|dir| dir.join("gitools")))
+        .or_else(|| var("HOME").map(|home| home.join(".config").join("gitools")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = toml::from_str("[branches]\nprotected = ['release/*']").unwrap();
+        assert_eq!(config.branches.protected, vec!["release/*"]);
+
+        let config: Config = toml::from_str("").unwrap();
+        assert!(config.branches.protected.is_empty());
+
+        assert!(toml::from_str::<Config>("[branches]\nprotect = ['main']").is_err());
+    }
+}
@@ -22,6 +22,28 @@ fn get_section_name(s: &str) -> (String, Option<String>) {
     (section_name, description)
 }
 
+/// Unescape a value as written by git: remove the double quotes and replace the escape sequences
+/// (`\n`, `\t`, `\"` and `\\`)
+fn unescape(value: &str) -> String {
+    let mut result = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {}
+            '\\' => match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some(other) => result.push(other),
+                None => {}
+            },
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
 #[derive(Debug)]
 pub enum OptionValue {
     String(String),
@@ -147,6 +169,23 @@ impl GitConfig {
         None
     }
 
+    /// Get the descriptions of the branches (`branch.<name>.description`), by branch name
+    pub fn get_branch_descriptions(&self) -> HashMap<String, String> {
+        let mut descriptions = HashMap::new();
+
+        if let Some(branches) = self.query("branch") {
+            for branch in branches {
+                if let (Some(name), Some(OptionValue::String(description))) =
+                    (branch.description.clone(), branch.query("description"))
+                {
+                    descriptions.insert(name, unescape(description));
+                }
+            }
+        }
+
+        descriptions
+    }
+
     /// Get all remotes of the repository
     pub fn get_remotes(&self) -> Vec<(String, String)> {
         let mut remotes_list = Vec::new();
@@ -164,3 +203,15 @@ impl GitConfig {
         remotes_list
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::unescape;
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape("plain value"), "plain value");
+        assert_eq!(unescape(r#""login page; \"v2\"""#), r#"login page; "v2""#);
+        assert_eq!(unescape(r"first line\nsecond line\n"), "first line\nsecond line\n");
+    }
+}
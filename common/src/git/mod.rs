@@ -7,7 +7,7 @@ use {
     lool::{cli::stylize::stylize, fail, s},
     std::{
         path::PathBuf,
-        process::{Command, Stdio},
+        process::{Command, Output, Stdio},
     },
 };
 
@@ -115,6 +115,20 @@ impl Git {
         Ok(s!(String::from_utf8_lossy(&output.stdout)))
     }
 
+    /// Executes a git command and captures its output instead of printing it.
+    ///
+    /// Unlike `exec_and_get_result`, a non-zero exit status is not considered an error, so the
+    /// caller can inspect the status along with stdout and stderr.
+    pub fn exec_and_capture(&self, cmd: &str, args: &[String]) -> Result<Output> {
+        let output = Command::new("git")
+            .arg(cmd)
+            .args(args)
+            .current_dir(self.config.get_repo_path())
+            .output()?;
+
+        Ok(output)
+    }
+
     /// Executes a git command and prints the output in real-time.
     /// If the command fails, returns Err.
     pub fn exec(&self, cmd: &str, args: &Vec<String>) -> Result<()> {
@@ -20,4 +20,6 @@ pub mod cli {
     }
 }
 
+pub mod config;
 pub mod git;
+pub mod protected;
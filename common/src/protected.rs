@@ -0,0 +1,55 @@
+//! # common/protected
+//!
+//! Protected branches, which gitools never deletes and asks before rewriting. They are given as
+//! glob patterns (e.g. `release/*`) in the `gitools.protected` git config, which can be set
+//! several times:
+//!
+//! ```sh
+//! git config --add gitools.protected 'release/*'
+//! ```
+//!
+//! or in the `[branches]` table of the gitools config file (see [`crate::config`]). `main` and
+//! `master` are always protected.
+
+use {
+    crate::{config::Config, git},
+    eyre::Result,
+    glob::Pattern,
+};
+
+const DEFAULT_PATTERNS: [&str; 2] = ["main", "master"];
+
+pub struct Protected(Vec<Pattern>);
+
+impl Protected {
+    /// Read the protected patterns from the git config and the gitools config file, along with
+    /// the default ones
+    pub fn load() -> Result<Self> {
+        let configured =
+            git::exec("config", vec!["--get-all", "gitools.protected"]).unwrap_or_default();
+        let config = Config::load()?;
+
+        Ok(Self::from_patterns(
+            DEFAULT_PATTERNS
+                .into_iter()
+                .chain(configured.lines())
+                .chain(config.branches.protected.iter().map(String::as_str)),
+        ))
+    }
+
+    /// Build the matcher from a list of glob patterns, ignoring the invalid ones
+    pub fn from_patterns<'a>(patterns: impl IntoIterator<Item = &'a str>) -> Self {
+        Protected(
+            patterns
+                .into_iter()
+                .map(str::trim)
+                .filter(|pattern| !pattern.is_empty())
+                .filter_map(|pattern| Pattern::new(pattern).ok())
+                .collect(),
+        )
+    }
+
+    pub fn matches(&self, branch: &str) -> bool {
+        self.0.iter().any(|pattern| pattern.matches(branch))
+    }
+}
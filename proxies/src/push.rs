@@ -1,8 +1,173 @@
-use eyre::Result;
+//! Proxify the `git push` command.
+//!
+//! Force-pushing to a protected branch (see [`common::protected`]) asks for confirmation first,
+//! whether it's forced with `--force`, `--force-with-lease` or a `+<refspec>`.
+
+use {
+    common::{
+        cli::action::{self, Action},
+        git,
+        protected::Protected,
+    },
+    eyre::Result,
+    inquire::Confirm,
+    lool::{cli::stylize::stylize, s},
+};
 
 const COMMAND: &str = "push";
 
-/// Proxify the `git push` command.
+/// Options of `git push` that take their value as a separate argument
+const OPTIONS_WITH_VALUE: [&str; 5] = ["-o", "--push-option", "--repo", "--receive-pack", "--exec"];
+
+/// A branch a push would force-update
+#[derive(Debug, PartialEq)]
+enum Target {
+    /// the branch the current one is pushed to
+    Current,
+    /// every local branch (`--all`, `--mirror`)
+    All,
+    Branch(String),
+}
+
 fn main() -> Result<()> {
+    if let Action::Cmd { args, .. } = action::get(COMMAND) {
+        let targets = force_targets(&args);
+
+        if !targets.is_empty() {
+            let protected = Protected::load()?;
+            let branches: Vec<String> = targets
+                .into_iter()
+                .flat_map(resolve)
+                .filter(|branch| protected.matches(branch))
+                .collect();
+
+            if !branches.is_empty() && !confirm(&branches) {
+                eprintln!("{}", stylize("Aborted", "red"));
+                std::process::exit(1);
+            }
+        }
+    }
+
     proxies::handle(COMMAND)
 }
+
+/// Ask whether to force-push to the protected branches; refused if it can't be asked
+fn confirm(branches: &[String]) -> bool {
+    println!(
+        "{} {}",
+        stylize("You are about to force-push to protected branches:", "yellow"),
+        stylize(branches.join(", "), "+bold")
+    );
+
+    Confirm::new("Force-push anyway?").with_default(false).prompt().unwrap_or(false)
+}
+
+/// The branches force-updated by `git push <args>` (none if nothing is forced)
+fn force_targets(args: &[String]) -> Vec<Target> {
+    let mut force = false;
+    let mut all = false;
+    let mut positional = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--force" => force = true,
+            arg if arg.starts_with("--force-with-lease") => force = true,
+            "--all" | "--branches" | "--mirror" => all = true,
+            arg if OPTIONS_WITH_VALUE.contains(&arg) => {
+                args.next();
+            }
+            // clusters of short flags, e.g. `-uf`
+            arg if arg.starts_with('-') && !arg.starts_with("--") => force |= arg.contains('f'),
+            arg if arg.starts_with('-') => {}
+            arg => positional.push(arg),
+        }
+    }
+
+    // the first positional argument is the remote, the rest are refspecs
+    let refspecs: Vec<&str> = positional.into_iter().skip(1).collect();
+
+    if all {
+        return if force { vec![Target::All] } else { vec![] };
+    }
+
+    if refspecs.is_empty() {
+        return if force { vec![Target::Current] } else { vec![] };
+    }
+
+    refspecs
+        .into_iter()
+        .filter_map(|refspec| {
+            let (plus, refspec) = match refspec.strip_prefix('+') {
+                Some(refspec) => (true, refspec),
+                None => (false, refspec),
+            };
+
+            let (src, dst) = refspec.split_once(':').unwrap_or((refspec, refspec));
+
+            // `:<branch>` deletes the branch, and tags aren't branches
+            if !(force || plus) || src.is_empty() || dst.starts_with("refs/tags/") {
+                return None;
+            }
+
+            match dst.strip_prefix("refs/heads/").unwrap_or(dst) {
+                "HEAD" => Some(Target::Current),
+                branch => Some(Target::Branch(s!(branch))),
+            }
+        })
+        .collect()
+}
+
+/// The branch names of a target
+fn resolve(target: Target) -> Vec<String> {
+    match target {
+        Target::Branch(branch) => vec![branch],
+        Target::All => git::exec("for-each-ref", vec!["--format=%(refname:short)", "refs/heads"])
+            .map(|output| output.lines().map(String::from).collect())
+            .unwrap_or_default(),
+        Target::Current => {
+            // the branch it's pushed to (e.g. `origin/main`), or the current one if there is none
+            let push = git::exec("rev-parse", vec!["--abbrev-ref", "@{push}"])
+                .ok()
+                .and_then(|push| push.trim().split_once('/').map(|(_, branch)| s!(branch)));
+            let current = || {
+                git::exec("symbolic-ref", vec!["-q", "--short", "HEAD"])
+                    .ok()
+                    .map(|head| s!(head.trim()))
+            };
+
+            push.or_else(current).into_iter().collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{force_targets, Target},
+        lool::s,
+    };
+
+    fn targets(args: &str) -> Vec<Target> {
+        force_targets(&args.split_whitespace().map(String::from).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_force_targets() {
+        assert_eq!(targets(""), vec![]);
+        assert_eq!(targets("origin main"), vec![]);
+        assert_eq!(targets("-f"), vec![Target::Current]);
+        assert_eq!(targets("-uf origin HEAD"), vec![Target::Current]);
+        assert_eq!(
+            targets("--force-with-lease=main origin main"),
+            vec![Target::Branch(s!("main"))]
+        );
+        assert_eq!(targets("origin +feat main"), vec![Target::Branch(s!("feat"))]);
+        assert_eq!(
+            targets("-f -o ci.skip origin feat:refs/heads/release/1.0 :old"),
+            vec![Target::Branch(s!("release/1.0"))]
+        );
+        assert_eq!(targets("--force --all origin"), vec![Target::All]);
+        assert_eq!(targets("--force origin refs/tags/v1.0"), vec![]);
+    }
+}